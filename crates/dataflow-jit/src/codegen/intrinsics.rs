@@ -400,6 +400,7 @@ intrinsics! {
 
     // String functions
     string_eq = fn(str, str) -> bool,
+    string_eq_slice = fn(str, ptr, usize) -> bool,
     string_lt = fn(str, str) -> bool,
     string_cmp = fn(str, str) -> i8,
     string_clone = fn(str) -> str,
@@ -575,6 +576,12 @@ extern "C" fn string_eq(lhs: ThinStrRef, rhs: ThinStrRef) -> bool {
     lhs == rhs
 }
 
+/// Returns `true` if `lhs` is equal to the string slice `rhs`
+unsafe extern "C" fn string_eq_slice(lhs: ThinStrRef, ptr: *const u8, len: usize) -> bool {
+    let rhs = unsafe { str_from_raw_parts(ptr, len) };
+    lhs.as_str() == rhs
+}
+
 /// Returns `true` if `lhs` is less than `rhs`
 // FIXME: Technically this can unwind
 extern "C" fn string_lt(lhs: ThinStrRef, rhs: ThinStrRef) -> bool {
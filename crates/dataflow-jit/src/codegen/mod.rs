@@ -22,7 +22,7 @@ use crate::{
         intrinsics::{ImportIntrinsics, Intrinsics},
         layout::MemoryEntry,
         pretty_clif::CommentWriter,
-        utils::FunctionBuilderExt,
+        utils::{compile_thinstr_eq_literal, FunctionBuilderExt},
     },
     ir::{
         block::ParamType, BinaryOp, BinaryOpKind, BlockId, Branch, Cast, ColumnType, Constant,
@@ -495,6 +495,9 @@ impl Codegen {
                         }
 
                         Expr::Constant(constant) => {
+                            if let Constant::String(string) = constant {
+                                ctx.string_constants.insert(expr_id, string.clone());
+                            }
                             let value = ctx.constant(constant, &mut builder);
                             ctx.add_expr(expr_id, value, constant.column_type(), None);
                         }
@@ -593,6 +596,8 @@ struct CodegenCtx<'a> {
     expr_types: BTreeMap<ExprId, ColumnType>,
     expr_layouts: BTreeMap<ExprId, LayoutId>,
     readonly_exprs: BTreeSet<ExprId>,
+    // Contents of string constants, comparisons against them are inlined
+    string_constants: BTreeMap<ExprId, String>,
     stack_slots: BTreeMap<ExprId, StackSlot>,
     function_inputs: BTreeMap<ExprId, InputFlags>,
     imports: ImportIntrinsics,
@@ -621,6 +626,7 @@ impl<'a> CodegenCtx<'a> {
             expr_types: BTreeMap::new(),
             expr_layouts: BTreeMap::new(),
             readonly_exprs: BTreeSet::new(),
+            string_constants: BTreeMap::new(),
             stack_slots: BTreeMap::new(),
             function_inputs: BTreeMap::new(),
             imports,
@@ -1775,6 +1781,10 @@ impl<'a> CodegenCtx<'a> {
     ) -> Value {
         // Strings
         if ty.is_string() {
+            if let Some(are_equal) = self.string_eq_constant(lhs, lhs_id, rhs, rhs_id, builder) {
+                return are_equal;
+            }
+
             let compare_contents = builder.create_block();
             let actually_compare = builder.create_block();
             let result_block = builder.create_block();
@@ -1833,6 +1843,50 @@ impl<'a> CodegenCtx<'a> {
         }
     }
 
+    /// Compares two strings for equality if either of them is a string
+    /// constant, returning `None` otherwise
+    ///
+    /// Comparisons against a constant are compiled by
+    /// [`compile_thinstr_eq_literal()`], which checks the string's length
+    /// and compares short constants inline instead of calling `memcmp()`
+    fn string_eq_constant(
+        &mut self,
+        lhs: Value,
+        lhs_id: ExprId,
+        rhs: Value,
+        rhs_id: ExprId,
+        builder: &mut FunctionBuilder<'_>,
+    ) -> Option<Value> {
+        let (string, literal, literal_ptr) = match (
+            self.string_constants.get(&lhs_id),
+            self.string_constants.get(&rhs_id),
+        ) {
+            // Both sides are constants, fold the comparison
+            (Some(lhs_literal), Some(rhs_literal)) => {
+                return Some(if lhs_literal == rhs_literal {
+                    builder.true_byte()
+                } else {
+                    builder.false_byte()
+                });
+            }
+            (None, Some(literal)) => (lhs, literal.clone(), rhs),
+            (Some(literal), None) => (rhs, literal.clone(), lhs),
+            (None, None) => return None,
+        };
+
+        let ptr_ty = self.pointer_type();
+        let (imports, module) = (&mut self.imports, &mut *self.module);
+        let are_equal =
+            compile_thinstr_eq_literal(string, false, &literal, builder, |string, builder| {
+                // String constants are compiled to a pointer to their bytes
+                let len = builder.ins().iconst(ptr_ty, literal.len() as i64);
+                let string_eq_slice = imports.get("string_eq_slice", module, builder.func);
+                builder.call_fn(string_eq_slice, &[string, literal_ptr, len])
+            });
+
+        Some(are_equal)
+    }
+
     fn binop_neq(
         &mut self,
         ty: ColumnType,
@@ -1844,6 +1898,10 @@ impl<'a> CodegenCtx<'a> {
     ) -> Value {
         // Strings
         if ty.is_string() {
+            if let Some(are_equal) = self.string_eq_constant(lhs, lhs_id, rhs, rhs_id, builder) {
+                return builder.ins().bxor_imm(are_equal, 1);
+            }
+
             let compare_contents = builder.create_block();
             let actually_compare = builder.create_block();
             let result_block = builder.create_block();
@@ -1,17 +1,25 @@
 #![cfg(test)]
 
 use crate::{
-    codegen::{Codegen, CodegenConfig},
+    codegen::{
+        utils::{compile_thinstr_eq_literal, FunctionBuilderExt},
+        Codegen, CodegenConfig,
+    },
     ir::{
         exprs::{ArgType, Call},
-        ColumnType, Constant, FunctionBuilder, RowLayoutBuilder, RowLayoutCache,
+        ColumnType, Constant, FunctionBuilder, LayoutId, RowLayoutBuilder, RowLayoutCache,
     },
     row::UninitRow,
     thin_str::ThinStrRef,
     utils, ThinStr,
 };
 use chrono::{Datelike, Utc};
-use std::mem::transmute;
+use cranelift::{
+    codegen::ir::UserFuncName,
+    prelude::{types, AbiParam, FunctionBuilder as ClifFunctionBuilder, InstBuilder, MemFlags},
+};
+use cranelift_module::{FuncId, Module};
+use std::mem::{size_of_val, transmute};
 
 #[test]
 fn block_param_phi() {
@@ -1147,3 +1155,268 @@ mod proptests {
         f64 = F64,
     }
 }
+
+/// Compiles `fn(*const u8) -> bool`, which compares the string in the first
+/// column of the given row against `literal` using
+/// [`compile_thinstr_eq_literal()`](super::utils::compile_thinstr_eq_literal)
+fn codegen_thinstr_eq_literal(codegen: &mut Codegen, layout_id: LayoutId, literal: &str) -> FuncId {
+    let ptr_ty = codegen.module.isa().pointer_type();
+    let mut signature = codegen.module.make_signature();
+    signature.params.push(AbiParam::new(ptr_ty));
+    signature.returns.push(AbiParam::new(types::I8));
+
+    let func_id = codegen
+        .module
+        .declare_anonymous_function(&signature)
+        .unwrap();
+    codegen.module_ctx.func.signature = signature;
+    codegen.module_ctx.func.name = UserFuncName::user(0, func_id.as_u32());
+
+    let mut imports = codegen.intrinsics.import(None);
+    {
+        let mut builder =
+            ClifFunctionBuilder::new(&mut codegen.module_ctx.func, &mut codegen.function_ctx);
+        let entry = builder.create_entry_block();
+        let row = builder.block_params(entry)[0];
+
+        let layout = codegen.layout_cache.layout_of(layout_id);
+        let string = builder.ins().load(
+            ptr_ty,
+            MemFlags::trusted().with_readonly(),
+            row,
+            layout.offset_of(0) as i32,
+        );
+        let module = &mut codegen.module;
+        let are_equal = compile_thinstr_eq_literal(
+            string,
+            layout.is_nullable(0),
+            literal,
+            &mut builder,
+            |string, builder| {
+                // The literal outlives the compiled function in all tests
+                let ptr = builder.ins().iconst(ptr_ty, literal.as_ptr() as i64);
+                let len = builder.ins().iconst(ptr_ty, literal.len() as i64);
+                let string_eq_slice = imports.get("string_eq_slice", module, builder.func);
+                builder.call_fn(string_eq_slice, &[string, ptr, len])
+            },
+        );
+        builder.ins().return_(&[are_equal]);
+
+        builder.seal_all_blocks();
+        builder.finalize();
+    }
+    codegen.finalize_function(func_id);
+
+    func_id
+}
+
+fn thinstr_eq_literal(literal: &str, nullable: bool, strings: &[(Option<&str>, bool)]) {
+    utils::test_logger();
+
+    let layout_cache = RowLayoutCache::new();
+    let string = layout_cache.add(
+        RowLayoutBuilder::new()
+            .with_column(ColumnType::String, nullable)
+            .build(),
+    );
+
+    let mut codegen = Codegen::new(layout_cache, CodegenConfig::debug());
+    let function = codegen_thinstr_eq_literal(&mut codegen, string, literal);
+
+    let (jit, layout_cache) = codegen.finalize_definitions();
+    {
+        let eq_literal = unsafe {
+            transmute::<*const u8, extern "C" fn(*const u8) -> bool>(
+                jit.get_finalized_function(function),
+            )
+        };
+
+        let layout = layout_cache.layout_of(string);
+        let mut row = [0usize; 4];
+        assert!(layout.size() as usize <= size_of_val(&row));
+        let column = unsafe {
+            row.as_mut_ptr()
+                .cast::<u8>()
+                .add(layout.offset_of(0) as usize)
+        };
+
+        for &(value, expected) in strings {
+            match value {
+                Some(value) => unsafe { column.cast::<ThinStr>().write(ThinStr::from(value)) },
+                None => unsafe { column.cast::<usize>().write(0) },
+            }
+
+            assert_eq!(
+                eq_literal(row.as_ptr().cast()),
+                expected,
+                "{value:?} == {literal:?}",
+            );
+
+            if value.is_some() {
+                unsafe { drop(column.cast::<ThinStr>().read()) };
+            }
+        }
+    }
+    unsafe { jit.free_memory() };
+}
+
+#[test]
+fn thinstr_eq_empty_literal() {
+    thinstr_eq_literal("", false, &[(Some(""), true), (Some("a"), false)]);
+}
+
+#[test]
+fn thinstr_eq_short_literals() {
+    // Exercise every chunk size, including overlapping chunks
+    for literal in [
+        "a",
+        "ab",
+        "abc",
+        "abcd",
+        "abcdefg",
+        "abcdefgh",
+        "abcdefghi",
+        "abcdefghijklmnop",
+    ] {
+        let mut content_mismatch = literal.to_owned().into_bytes();
+        *content_mismatch.last_mut().unwrap() = b'z';
+        let content_mismatch = String::from_utf8(content_mismatch).unwrap();
+
+        let mut first_mismatch = literal.to_owned().into_bytes();
+        first_mismatch[0] = b'z';
+        let first_mismatch = String::from_utf8(first_mismatch).unwrap();
+
+        let longer = format!("{literal}a");
+
+        thinstr_eq_literal(
+            literal,
+            false,
+            &[
+                (Some(literal), true),
+                (Some(&content_mismatch), false),
+                (Some(&first_mismatch), false),
+                (Some(&literal[1..]), false),
+                (Some(&longer), false),
+            ],
+        );
+    }
+}
+
+#[test]
+fn thinstr_eq_long_literal() {
+    let literal = "the quick brown fox jumps over the lazy dog";
+    thinstr_eq_literal(
+        literal,
+        false,
+        &[
+            (Some(literal), true),
+            (Some("the quick brown fox jumps over the lazy cat"), false),
+            (Some("the quick brown fox"), false),
+            (Some(""), false),
+        ],
+    );
+}
+
+#[test]
+fn thinstr_eq_nullable_literal() {
+    thinstr_eq_literal(
+        "foobar",
+        true,
+        &[
+            (Some("foobar"), true),
+            (Some("foobaz"), false),
+            (None, false),
+        ],
+    );
+    thinstr_eq_literal("", true, &[(Some(""), true), (None, false)]);
+}
+
+#[test]
+fn string_eq_constant() {
+    utils::test_logger();
+
+    let layout_cache = RowLayoutCache::new();
+    let string = layout_cache.add(
+        RowLayoutBuilder::new()
+            .with_column(ColumnType::String, false)
+            .build(),
+    );
+    let bools = layout_cache.add(
+        RowLayoutBuilder::new()
+            .with_column(ColumnType::Bool, false)
+            .with_column(ColumnType::Bool, false)
+            .with_column(ColumnType::Bool, false)
+            .build(),
+    );
+
+    // Short literals are compared inline, long ones by `string_eq_slice`
+    for literal in ["", "abc", "the quick brown fox jumps over the lazy dog"] {
+        // Computes `string == literal`, `literal != string` and
+        // `literal == literal`
+        let function = {
+            let mut builder = FunctionBuilder::new(layout_cache.clone());
+            let input = builder.add_input(string);
+            let output = builder.add_output(bools);
+
+            let value = builder.load(input, 0);
+            let constant = builder.constant(Constant::String(literal.to_owned()));
+            let eq = builder.eq(value, constant);
+            builder.store(output, 0, eq);
+            let neq = builder.neq(constant, value);
+            builder.store(output, 1, neq);
+            let other_constant = builder.constant(Constant::String(literal.to_owned()));
+            let constants_eq = builder.eq(constant, other_constant);
+            builder.store(output, 2, constants_eq);
+            builder.ret_unit();
+
+            builder.build()
+        };
+
+        let mut codegen = Codegen::new(layout_cache.clone(), CodegenConfig::debug());
+        let function = codegen.codegen_func("string_eq_constant", &function);
+
+        let (jit, native_layout_cache) = codegen.finalize_definitions();
+        {
+            let string_eq_constant = unsafe {
+                transmute::<*const u8, extern "C" fn(*const u8, *mut u8)>(
+                    jit.get_finalized_function(function),
+                )
+            };
+
+            let string_layout = native_layout_cache.layout_of(string);
+            let bools_layout = native_layout_cache.layout_of(bools);
+            let mut input = [0usize; 4];
+            let mut output = [0usize; 4];
+            assert!(string_layout.size() as usize <= size_of_val(&input));
+            assert!(bools_layout.size() as usize <= size_of_val(&output));
+
+            let mismatch = format!("{literal}a");
+            for (value, expected) in [(literal, true), (&*mismatch, false), ("xyz", false)] {
+                let column = unsafe {
+                    input
+                        .as_mut_ptr()
+                        .cast::<u8>()
+                        .add(string_layout.offset_of(0) as usize)
+                };
+                unsafe { column.cast::<ThinStr>().write(ThinStr::from(value)) };
+
+                string_eq_constant(input.as_ptr().cast(), output.as_mut_ptr().cast());
+
+                let read_bool = |column| unsafe {
+                    output
+                        .as_ptr()
+                        .cast::<u8>()
+                        .add(bools_layout.offset_of(column) as usize)
+                        .cast::<bool>()
+                        .read()
+                };
+                assert_eq!(read_bool(0), expected, "{value:?} == {literal:?}");
+                assert_eq!(read_bool(1), !expected, "{literal:?} != {value:?}");
+                assert!(read_bool(2), "{literal:?} == {literal:?}");
+
+                unsafe { drop(column.cast::<ThinStr>().read()) };
+            }
+        }
+        unsafe { jit.free_memory() };
+    }
+}
@@ -1,4 +1,4 @@
-use crate::codegen::NativeLayout;
+use crate::{codegen::NativeLayout, ThinStr};
 use cranelift::{
     codegen::ir::{FuncRef, Inst},
     prelude::{types, Block, FunctionBuilder, InstBuilder, IntCC, MemFlags, Type, Value},
@@ -30,10 +30,20 @@ pub(crate) trait FunctionBuilderExt {
 
     fn value_type(&self, value: Value) -> Type;
 
+    /// Creates a `0.0` constant of the given float type
+    ///
+    /// Only `F32` and `F64` are supported since they're the only scalar float
+    /// types Cranelift provides, panics on any other type
     fn float_zero(&mut self, ty: Type) -> Value;
 
+    /// Creates a `1.0` constant of the given float type
+    ///
+    /// Only `F32` and `F64` are supported, panics on any other type
     fn float_one(&mut self, ty: Type) -> Value;
 
+    /// Creates a `π` constant of the given float type
+    ///
+    /// Only `F32` and `F64` are supported, panics on any other type
     fn float_pi(&mut self, ty: Type) -> Value;
 }
 
@@ -255,3 +265,143 @@ pub(super) fn set_column_null(
         .ins()
         .store(dest_flags, bitset, dest, bitset_offset as i32);
 }
+
+/// The longest literal [`compile_thinstr_eq_literal()`] compares inline,
+/// longer literals are compared by a runtime call
+pub(super) const MAX_INLINE_LITERAL_LEN: usize = 16;
+
+/// Compares `string`, a `ThinStr` pointer, against `literal`, returning a
+/// boolean byte that's `true` if they're equal
+///
+/// The string's length is checked against the literal's length before looking
+/// at any of its bytes. Literals of up to [`MAX_INLINE_LITERAL_LEN`] bytes are
+/// then compared inline by loading the string's data in (possibly
+/// overlapping) word-sized chunks and comparing them against constants, which
+/// never reads past the end of the string since its length is already known
+/// to match. Longer literals are handed off to `compare_long`, which is given
+/// the string's `ThinStr` pointer and should return a boolean byte (usually by
+/// calling an intrinsic like `string_eq_slice`). If `nullable` is set,
+/// `string` may be a null pointer, which is never equal to a literal.
+pub(super) fn compile_thinstr_eq_literal<F>(
+    string: Value,
+    nullable: bool,
+    literal: &str,
+    builder: &mut FunctionBuilder<'_>,
+    compare_long: F,
+) -> Value
+where
+    F: FnOnce(Value, &mut FunctionBuilder<'_>) -> Value,
+{
+    let ptr_ty = builder.value_type(string);
+    let flags = MemFlags::trusted().with_readonly();
+
+    let return_block = builder.create_block();
+    builder.append_block_param(return_block, types::I8);
+
+    // Null strings are represented by null pointers and are never equal to
+    // the literal
+    if nullable {
+        let non_null = builder.create_block();
+        let false_val = builder.false_byte();
+        builder
+            .ins()
+            .brif(string, non_null, &[], return_block, &[false_val]);
+        builder.switch_to_block(non_null);
+        builder.seal_block(non_null);
+    }
+
+    // Compare the string's length against the literal's length
+    let length = builder
+        .ins()
+        .load(ptr_ty, flags, string, ThinStr::length_offset() as i32);
+    let lengths_equal = builder
+        .ins()
+        .icmp_imm(IntCC::Equal, length, literal.len() as i64);
+
+    let are_equal = if literal.is_empty() {
+        lengths_equal
+    } else {
+        let compare_bytes = builder.create_block();
+        builder.ins().brif(
+            lengths_equal,
+            compare_bytes,
+            &[],
+            return_block,
+            &[lengths_equal],
+        );
+        builder.switch_to_block(compare_bytes);
+        builder.seal_block(compare_bytes);
+
+        if literal.len() <= MAX_INLINE_LITERAL_LEN {
+            compare_literal_bytes(string, literal.as_bytes(), builder)
+        } else {
+            compare_long(string, builder)
+        }
+    };
+
+    builder.ins().jump(return_block, &[are_equal]);
+
+    builder.switch_to_block(return_block);
+    builder.seal_block(return_block);
+    builder.block_params(return_block)[0]
+}
+
+/// Compares the data of `string` against the (non-empty) `literal`, assumes
+/// that the string's length is equal to the literal's length
+///
+/// Uses at most two loads per word size: a string of 5 to 8 bytes is covered
+/// by two 4-byte loads at the start and end of the string (which overlap for
+/// lengths under 8), a string of 9 to 16 bytes by two 8-byte loads and so on
+fn compare_literal_bytes(
+    string: Value,
+    literal: &[u8],
+    builder: &mut FunctionBuilder<'_>,
+) -> Value {
+    debug_assert!(!literal.is_empty() && literal.len() <= MAX_INLINE_LITERAL_LEN);
+
+    let chunk = match literal.len() {
+        1 => 1,
+        2..=3 => 2,
+        4..=7 => 4,
+        _ => 8,
+    };
+    let chunk_ty = Type::int_with_byte_size(chunk as u16).unwrap();
+
+    // The string's data isn't guaranteed to be aligned to the chunk size
+    let mut flags = MemFlags::new();
+    flags.set_notrap();
+    flags.set_readonly();
+    let data_offset = ThinStr::pointer_offset();
+
+    let mut offsets = vec![0];
+    if literal.len() > chunk {
+        offsets.push(literal.len() - chunk);
+    }
+
+    // OR together the differences between each chunk and the literal, the
+    // strings are equal if the result is zero
+    let mut difference = None;
+    for offset in offsets {
+        // Loads use the target's byte order, so the expected value has to as well
+        let bytes = &literal[offset..offset + chunk];
+        let expected = match chunk {
+            1 => bytes[0] as i64,
+            2 => u16::from_ne_bytes(bytes.try_into().unwrap()) as i64,
+            4 => u32::from_ne_bytes(bytes.try_into().unwrap()) as i64,
+            _ => i64::from_ne_bytes(bytes.try_into().unwrap()),
+        };
+        let expected = builder.ins().iconst(chunk_ty, expected);
+
+        let loaded = builder
+            .ins()
+            .load(chunk_ty, flags, string, (data_offset + offset) as i32);
+        let diff = builder.ins().bxor(loaded, expected);
+
+        difference = Some(match difference {
+            Some(difference) => builder.ins().bor(difference, diff),
+            None => diff,
+        });
+    }
+
+    builder.ins().icmp_imm(IntCC::Equal, difference.unwrap(), 0)
+}
@@ -36,6 +36,16 @@ static EMPTY: StrHeader = StrHeader {
     _data: [],
 };
 
+/// The smallest capacity allocated when a string grows
+///
+/// Incrementally built strings (e.g. with repeated calls to
+/// [`ThinStr::push()`]) would otherwise reallocate on nearly every push while
+/// they're small, so growing a string always allocates at least enough to fill
+/// a 64 byte cache line with the header and string data. Exact constructors
+/// such as [`ThinStr::with_capacity()`] and [`ThinStr::shrink_to_fit()`] don't
+/// apply this floor
+const MIN_GROWTH_CAPACITY: usize = 64 - size_of::<StrHeader>();
+
 #[repr(C)]
 struct StrHeader {
     length: usize,
@@ -126,6 +136,29 @@ impl ThinStr {
         unsafe { str::from_utf8_unchecked(self.as_bytes()) }
     }
 
+    /// Returns the longest prefix of the string that is at most `max_bytes`
+    /// bytes long and ends on a char boundary
+    ///
+    /// Useful for truncating long strings (e.g. when logging them) without
+    /// splitting a multibyte character
+    #[inline]
+    pub fn as_str_truncated(&self, max_bytes: usize) -> &str {
+        let string = self.as_str();
+        if max_bytes >= string.len() {
+            return string;
+        }
+
+        // A char is at most 4 bytes long, so we'll find a boundary within
+        // the last 3 bytes before `max_bytes` at the latest
+        let mut end = max_bytes;
+        while !string.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        // Safety: `end` is a char boundary no larger than `string.len()`
+        unsafe { string.get_unchecked(..end) }
+    }
+
     #[inline]
     pub fn as_mut_bytes(&mut self) -> &mut [u8] {
         // Safety: All bytes up to self.len() are valid
@@ -166,6 +199,13 @@ impl ThinStr {
         unsafe { addr_of_mut!((*self.buf.as_ptr()).capacity).write(capacity) }
     }
 
+    /// Consumes the `ThinStr`, returning a raw pointer to its buffer
+    ///
+    /// The caller becomes responsible for the string's memory and should turn
+    /// the pointer back into a `ThinStr` with [`ThinStr::from_raw()`] in
+    /// order to free it. Strings without an allocation (such as those
+    /// created by [`ThinStr::new()`]) return a pointer to the static empty
+    /// string, which doesn't own any memory
     #[inline]
     pub fn into_raw(self) -> *mut () {
         let this = ManuallyDrop::new(self);
@@ -179,9 +219,13 @@ impl ThinStr {
     /// the allocated memory. For this to be safe, the memory must have been
     /// allocated in accordance with the memory layout used by `ThinStr`.
     ///
+    /// Pointers to the static empty string are accepted as well, the
+    /// resulting `ThinStr` never deallocates them
+    ///
     /// # Safety
     ///
-    /// The pointer passed must have come from [`ThinStr::into_raw()`]
+    /// The pointer passed must have come from [`ThinStr::into_raw()`] and
+    /// must not be passed to `from_raw()` more than once
     #[inline]
     pub unsafe fn from_raw(raw: *mut ()) -> Self {
         debug_assert!(
@@ -232,6 +276,12 @@ impl ThinStr {
     unsafe fn with_capacity_uninit(capacity: usize, length: usize) -> Self {
         debug_assert!(length <= capacity);
 
+        // Don't allocate empty strings, the sigil is the only string with a
+        // capacity of zero
+        if capacity == 0 {
+            return Self::new();
+        }
+
         let (capacity, layout) = Self::layout_for(capacity);
 
         debug_assert_ne!(layout.size(), 0);
@@ -323,10 +373,7 @@ impl ThinStr {
         // - https://stackoverflow.com/questions/1100311/what-is-the-ideal-growth-rate-for-a-dynamically-allocated-array
         //
         //  `x + (x >> 1)` is equivalent to `x * 1.5`
-        let capacity = max(
-            current + (current >> 1),
-            max(minimum, 64 - (size_of::<usize>() * 2)),
-        );
+        let capacity = max(current + (current >> 1), max(minimum, MIN_GROWTH_CAPACITY));
         // We align our strings to 16 bytes, so we can always take advantage of that
         // "extra" capacity we'll allocate
         let capacity = next_multiple_of(capacity, 16);
@@ -427,7 +474,11 @@ impl ThinStr {
     }
 
     pub fn shrink_to_fit(&mut self) {
-        if self.capacity() > self.len() {
+        // Empty strings release their allocation entirely, allocated strings
+        // must always have a non-zero capacity
+        if self.is_empty() {
+            *self = Self::new();
+        } else if self.capacity() > self.len() {
             debug_assert!(!self.is_sigil());
 
             let current_layout = unsafe { Self::layout_for_unchecked(self.capacity()) };
@@ -483,6 +534,9 @@ impl ThinStr {
             Some(length) => length,
             None => thin_str_concat_length_overflow(first.len(), second.len()),
         };
+        if length == 0 {
+            return Self::new();
+        }
 
         // Allocate a string with enough capacity to fit both strings
         let mut this = Self::with_capacity(length);
@@ -582,7 +636,10 @@ impl fmt::Write for ThinStr {
 
 impl Drop for ThinStr {
     fn drop(&mut self) {
-        if self.capacity() != 0 {
+        // The sigil empty string is a static and must never be deallocated
+        if !self.is_sigil() {
+            debug_assert!(self.capacity() != 0);
+
             // Safety: The current layout is valid since we must have created it in order to
             // make the current `ThinStr` and we're deallocating a valid allocation
             unsafe {
@@ -675,3 +732,122 @@ const fn next_multiple_of(lhs: usize, rhs: usize) -> usize {
         rem => lhs + (rhs - rem),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::thin_str::{ThinStr, MIN_GROWTH_CAPACITY};
+
+    #[test]
+    fn truncate_ascii() {
+        let string = ThinStr::from("hello world");
+        assert_eq!(string.as_str_truncated(5), "hello");
+        assert_eq!(string.as_str_truncated(0), "");
+        assert_eq!(string.as_str_truncated(11), "hello world");
+        assert_eq!(string.as_str_truncated(100), "hello world");
+        assert_eq!(ThinStr::new().as_str_truncated(10), "");
+    }
+
+    #[test]
+    fn empty_raw_roundtrip() {
+        let empty = ThinStr::new();
+        let raw = empty.into_raw();
+        let empty = unsafe { ThinStr::from_raw(raw) };
+        assert!(empty.is_empty());
+        assert!(empty.is_sigil());
+        drop(empty);
+
+        // Emptied strings keep their allocation until they're shrunk
+        let mut string = ThinStr::from("hello");
+        unsafe { string.set_len(0) };
+        let raw = string.into_raw();
+        let mut string = unsafe { ThinStr::from_raw(raw) };
+        assert!(!string.is_sigil());
+        string.shrink_to_fit();
+        assert!(string.is_sigil());
+        let string = unsafe { ThinStr::from_raw(string.into_raw()) };
+        drop(string);
+    }
+
+    // `clone_from()` allows a single scratch string to be reused when cloning
+    // many strings without allocating for each of them
+    #[test]
+    fn clone_from_reuses_allocation() {
+        let strings = ["hello world", "hello", "", "h€llo", "hello world"].map(ThinStr::from);
+
+        let mut scratch = ThinStr::new();
+        scratch.clone_from(&strings[0]);
+        assert_eq!(scratch, strings[0]);
+        let buffer = scratch.as_ptr();
+        let capacity = scratch.capacity();
+
+        // Strings no longer than the first one reuse its buffer
+        for string in &strings[1..] {
+            scratch.clone_from(string);
+            assert_eq!(scratch, *string);
+            assert_eq!(scratch.as_ptr(), buffer);
+            assert_eq!(scratch.capacity(), capacity);
+        }
+
+        // Longer strings grow the buffer
+        let long = ThinStr::from("a considerably longer string than the others");
+        scratch.clone_from(&long);
+        assert_eq!(scratch, long);
+        assert!(scratch.capacity() >= long.len());
+    }
+
+    #[test]
+    fn small_pushes_use_capacity_floor() {
+        let mut string = ThinStr::new();
+        string.push('a');
+        assert_eq!(string.capacity(), MIN_GROWTH_CAPACITY);
+
+        // Filling the string up to the floor doesn't reallocate
+        let buffer = string.as_ptr();
+        while string.len() < MIN_GROWTH_CAPACITY {
+            string.push_str("b");
+            assert_eq!(string.as_ptr(), buffer);
+            assert_eq!(string.capacity(), MIN_GROWTH_CAPACITY);
+        }
+
+        // Growing past the floor grows the string geometrically
+        string.push('c');
+        assert!(string.capacity() >= MIN_GROWTH_CAPACITY + (MIN_GROWTH_CAPACITY >> 1));
+    }
+
+    #[test]
+    fn exact_construction_stays_tight() {
+        // Exact capacities are only rounded up to the 16 byte alignment of the
+        // string's data
+        assert_eq!(ThinStr::from("a").capacity(), 16);
+        assert_eq!(ThinStr::from("hello world").capacity(), 16);
+        assert_eq!(ThinStr::from("hello world, hello").capacity(), 32);
+        assert_eq!(ThinStr::with_capacity(1).capacity(), 16);
+        assert_eq!(ThinStr::concat("a", "b").capacity(), 16);
+        assert_eq!(ThinStr::from("a").clone().capacity(), 16);
+
+        let mut string = ThinStr::new();
+        string.push_str("hello");
+        assert_eq!(string.capacity(), MIN_GROWTH_CAPACITY);
+        string.shrink_to_fit();
+        assert_eq!(string.capacity(), 16);
+        assert_eq!(string, ThinStr::from("hello"));
+    }
+
+    #[test]
+    fn truncate_multibyte() {
+        // `€` is encoded as 3 bytes
+        let string = ThinStr::from("a€b€");
+        assert_eq!(string.len(), 8);
+
+        // Exact char boundaries
+        assert_eq!(string.as_str_truncated(1), "a");
+        assert_eq!(string.as_str_truncated(4), "a€");
+        assert_eq!(string.as_str_truncated(5), "a€b");
+
+        // In the middle of a 3-byte char
+        assert_eq!(string.as_str_truncated(2), "a");
+        assert_eq!(string.as_str_truncated(3), "a");
+        assert_eq!(string.as_str_truncated(6), "a€b");
+        assert_eq!(string.as_str_truncated(7), "a€b");
+    }
+}
@@ -51,6 +51,35 @@ where
             .clone()
     }
 
+    /// Computes the set difference of `self` and `other`.
+    ///
+    /// For each pair of input batches `A` and `B`, the operator outputs the
+    /// tuples `(key, value, 1)` such that `(key, value)` has a positive weight
+    /// in `A` and does not have a positive weight in `B`.  Tuples that appear
+    /// in both batches cancel out regardless of their weights.  This matches
+    /// the semantics of SQL `EXCEPT`.
+    ///
+    /// Unlike [`minus`](`Self::minus`), which subtracts weights and can
+    /// produce negative or zero weights, the output of this operator is
+    /// always a set.  For instance, subtracting `{x => 1}` from `{x => 2}`
+    /// yields `{x => 1}` with `minus`, but `{}` with `stream_minus`, and
+    /// subtracting `{x => 1}` from `{}` yields `{x => -1}` with `minus`, but
+    /// `{}` with `stream_minus`.
+    ///
+    /// Like [`stream_distinct`](`Self::stream_distinct`), this operator
+    /// is not incremental: it processes each pair of batches independently.
+    /// Apply it to integrated streams to compute the difference between two
+    /// relations.
+    pub fn stream_minus(&self, other: &Stream<C, Z>) -> Stream<C, Z>
+    where
+        Z: IndexedZSet + Send,
+        Z::R: ZRingValue,
+    {
+        self.stream_distinct()
+            .minus(&other.stream_distinct())
+            .stream_distinct()
+    }
+
     /// Incrementally deduplicate input stream.
     ///
     /// This is an incremental version of the
@@ -889,6 +918,62 @@ mod test {
         circuit.kill().unwrap();
     }
 
+    #[test]
+    fn stream_minus_test() {
+        let output = Arc::new(Mutex::new(OrdIndexedZSet::empty(())));
+        let output_clone = output.clone();
+
+        let (mut circuit, (mut left, mut right)) = Runtime::init_circuit(4, move |circuit| {
+            let (left, left_handle) = circuit.add_input_indexed_zset::<usize, usize, isize>();
+            let (right, right_handle) = circuit.add_input_indexed_zset::<usize, usize, isize>();
+
+            left.integrate()
+                .stream_minus(&right.integrate())
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output_clone.lock().unwrap() = batch.clone();
+                    }
+                });
+
+            (left_handle, right_handle)
+        })
+        .unwrap();
+
+        // Key `1` overlaps: value `2` is removed regardless of its weight on
+        // either side.  Key `3` only appears on the left and key `4` only on
+        // the right.
+        left.append(&mut vec![
+            (1, (1, 1)),
+            (1, (2, 2)),
+            (2, (1, 1)),
+            (3, (5, 1)),
+        ]);
+        right.append(&mut vec![
+            (1, (2, 1)),
+            (1, (3, 1)),
+            (2, (1, 3)),
+            (4, (1, 1)),
+        ]);
+        circuit.step().unwrap();
+        assert_eq!(
+            &*output.lock().unwrap(),
+            &indexed_zset! { 1 => { 1 => 1 }, 3 => { 5 => 1 } }
+        );
+
+        // Non-positive weights on the left don't produce output, and removing
+        // a tuple from the right brings it back.
+        left.append(&mut vec![(1, (1, -1)), (1, (4, -1)), (1, (6, 1))]);
+        right.append(&mut vec![(2, (1, -3))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            &*output.lock().unwrap(),
+            &indexed_zset! { 1 => { 6 => 1 }, 2 => { 1 => 1 }, 3 => { 5 => 1 } }
+        );
+
+        circuit.kill().unwrap();
+    }
+
     use proptest::{collection, prelude::*};
 
     type TestZSet = OrdZSet<usize, isize>;
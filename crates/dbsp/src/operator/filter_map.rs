@@ -1,6 +1,7 @@
 //! Filter and transform data record-by-record.
 
 use crate::{
+    algebra::IndexedZSet,
     circuit::{
         operator_traits::{Operator, UnaryOperator},
         Circuit, OwnershipPreference, Scope, Stream,
@@ -291,6 +292,31 @@ where
     }
 }
 
+impl<C, B> Stream<C, B>
+where
+    C: Circuit,
+    B: IndexedZSet,
+{
+    /// Drop all keys that don't satisfy `predicate` from an indexed stream,
+    /// along with all of their values.
+    ///
+    /// Values and weights of the remaining keys are preserved.  Since the
+    /// operator only looks at keys, it can be applied to changes: insertions
+    /// and retractions of filtered out keys are dropped alike, so the output
+    /// never contains them.  Unlike [`FilterMap::filter`], `predicate` is
+    /// evaluated once per key rather than once per `(key, value)` pair.
+    pub fn filter_keys<F>(&self, predicate: F) -> Self
+    where
+        F: Fn(&B::Key) -> bool + 'static,
+    {
+        let filtered = self
+            .circuit()
+            .add_unary_operator(FilterKeys::new(predicate), &self.try_sharded_version());
+        filtered.mark_sharded_if(self);
+        filtered
+    }
+}
+
 /// Internal implementation for filtering [`BatchReader`]s
 pub struct FilterKeys<CI, CO, F> {
     filter: F,
@@ -325,12 +351,11 @@ impl<CI, CO, F> FilterKeys<CI, CO, F> {
         while consumer.key_valid() {
             let (key, mut values) = consumer.next_key();
 
-            // FIXME: We assume that this operator will only be used with `OrdZSet` and that
-            // it has either zero or one values, meaning that it'll skip any additional
-            // values
-            if (self.filter)(&key) && values.value_valid() {
-                let (value, weight, ()) = values.next_value();
-                builder.push((CO::item_from(key, value), weight));
+            if (self.filter)(&key) {
+                while values.value_valid() {
+                    let (value, weight, ()) = values.next_value();
+                    builder.push((CO::item_from(key.clone(), value), weight));
+                }
             }
         }
 
@@ -730,7 +755,7 @@ mod test {
         indexed_zset,
         operator::{FilterMap, Generator},
         trace::ord::OrdZSet,
-        zset, Circuit, RootCircuit,
+        zset, Circuit, OrdIndexedZSet, RootCircuit,
     };
     use std::vec;
 
@@ -887,4 +912,62 @@ mod test {
             circuit.step().unwrap();
         }
     }
+    #[test]
+    fn filter_keys_test() {
+        let circuit = RootCircuit::build(move |circuit| {
+            let input = || -> vec::IntoIter<OrdIndexedZSet<usize, String, isize>> {
+                vec![
+                    indexed_zset! {
+                        1 => {"a".to_string() => 1},
+                        2 => {"b".to_string() => 1, "c".to_string() => 2},
+                        3 => {"d".to_string() => -1},
+                        4 => {"e".to_string() => 1}
+                    },
+                    // Retractions of filtered out keys are dropped.
+                    indexed_zset! {
+                        1 => {"a".to_string() => -1},
+                        2 => {"c".to_string() => -2},
+                        4 => {"f".to_string() => 1}
+                    },
+                ]
+                .into_iter()
+            };
+            let expected = || -> vec::IntoIter<OrdIndexedZSet<usize, String, isize>> {
+                vec![
+                    indexed_zset! {
+                        2 => {"b".to_string() => 1, "c".to_string() => 2},
+                        4 => {"e".to_string() => 1}
+                    },
+                    indexed_zset! {
+                        2 => {"c".to_string() => -2},
+                        4 => {"f".to_string() => 1}
+                    },
+                ]
+                .into_iter()
+            };
+
+            // The filter is the only consumer of `owned`, so it receives its
+            // input by value, whereas `shared` is also consumed by `inspect`.
+            let mut owned_input = input();
+            let mut owned_output = expected();
+            circuit
+                .add_source(Generator::new(move || owned_input.next().unwrap()))
+                .filter_keys(|k| k % 2 == 0)
+                .inspect(move |batch| assert_eq!(batch, &owned_output.next().unwrap()));
+
+            let mut shared_input = input();
+            let mut shared_output = expected();
+            let shared = circuit.add_source(Generator::new(move || shared_input.next().unwrap()));
+            shared.inspect(|_| {});
+            shared
+                .filter_keys(|k| k % 2 == 0)
+                .inspect(move |batch| assert_eq!(batch, &shared_output.next().unwrap()));
+        })
+        .unwrap()
+        .0;
+
+        for _ in 0..2 {
+            circuit.step().unwrap();
+        }
+    }
 }
@@ -0,0 +1,284 @@
+use crate::{
+    algebra::{AddAssignByRef, HasOne, HasZero, IndexedZSet, ZRingValue},
+    trace::{Batch, BatchReader, Cursor},
+    DBData, DBWeight, OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::collections::BTreeMap;
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Estimates the `k` most frequent values in each group using the
+    /// Space-Saving algorithm.
+    ///
+    /// The frequency of a value is the sum of its positive weights across
+    /// all input batches seen so far; negative weights are ignored, since
+    /// the sketch cannot forget values.  At every step, for each group
+    /// modified by the input batch, the operator updates the estimate of the
+    /// group's top `k` values, represented as `(value, (count, error))`
+    /// tuples with weight `1`.  Like other operators, it outputs changes:
+    /// the previous estimate of each modified group is retracted and the new
+    /// one is inserted, so integrating the output yields the current
+    /// estimates of all groups.
+    ///
+    /// # Accuracy
+    ///
+    /// Each group is tracked by at most `capacity` counters, so memory use is
+    /// bounded by `capacity` values per group, regardless of the number of
+    /// distinct values in the stream.  When all counters are taken, a new
+    /// value replaces the value with the smallest count and inherits that
+    /// count as its `error`.  As a result:
+    ///
+    /// * `count` is an estimate that never underestimates the frequency of
+    ///   the value, and overestimates it by at most `error`.  `count - error`
+    ///   is a guaranteed lower bound on the frequency.
+    /// * `error` is bounded by `n / capacity`, where `n` is the total weight
+    ///   inserted into the group, so every value whose frequency exceeds
+    ///   `n / capacity` is guaranteed to be tracked.
+    ///
+    /// Larger `capacity` tightens the error bound at the cost of memory; a
+    /// few times `k` is usually sufficient for skewed distributions.
+    ///
+    /// Unlike [`topk_desc`](`Self::topk_desc`), the operator is not
+    /// incremental: its state only grows, and its output depends on the
+    /// order in which values arrive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity < k`.
+    pub fn approx_topk(
+        &self,
+        k: usize,
+        capacity: usize,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, (B::R, B::R)), B::R>> {
+        assert!(
+            capacity >= k,
+            "approx_topk capacity ({capacity}) must be at least k ({k})"
+        );
+
+        // The sketch of each group along with its last output.
+        let mut sketches: BTreeMap<B::Key, (SpaceSaving<B::Val, B::R>, TopK<B::Val, B::R>)> =
+            BTreeMap::new();
+
+        self.shard()
+            .apply_named("ApproxTopK", move |delta: &B| {
+                let mut tuples = Vec::new();
+                let mut inserts = Vec::new();
+                let mut cursor = delta.cursor();
+
+                while cursor.key_valid() {
+                    while cursor.val_valid() {
+                        let weight = cursor.weight();
+                        if weight.ge0() && !weight.is_zero() {
+                            inserts.push((cursor.val().clone(), weight));
+                        }
+                        cursor.step_val();
+                    }
+
+                    if !inserts.is_empty() {
+                        let key = cursor.key();
+                        let (sketch, top) = sketches
+                            .entry(key.clone())
+                            .or_insert_with(|| (SpaceSaving::new(capacity), Vec::new()));
+                        for (val, weight) in inserts.drain(..) {
+                            sketch.insert(val, weight);
+                        }
+                        let new_top = sketch.top(k);
+                        for (val, count, error) in top.drain(..) {
+                            tuples.push(((key.clone(), (val, (count, error))), -B::R::one()));
+                        }
+                        for (val, count, error) in new_top.iter() {
+                            tuples.push((
+                                (key.clone(), (val.clone(), (count.clone(), error.clone()))),
+                                B::R::one(),
+                            ));
+                        }
+                        *top = new_top;
+                    }
+
+                    cursor.step_key();
+                }
+
+                OrdIndexedZSet::from_tuples((), tuples)
+            })
+            .mark_sharded()
+    }
+}
+
+/// Estimated top values of a group as `(value, count, error)`, see
+/// [`SpaceSaving::top`].
+type TopK<V, R> = Vec<(V, R, R)>;
+
+/// Space-Saving sketch tracking approximate frequencies of up to `capacity`
+/// values.
+struct SpaceSaving<V, R> {
+    capacity: usize,
+    /// Maps tracked values to their `(count, error)`.
+    counters: BTreeMap<V, (R, R)>,
+}
+
+impl<V, R> SpaceSaving<V, R>
+where
+    V: DBData,
+    R: DBWeight + ZRingValue,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            counters: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `weight` occurrences of `val` to the sketch.
+    fn insert(&mut self, val: V, weight: R) {
+        if let Some((count, _)) = self.counters.get_mut(&val) {
+            count.add_assign_by_ref(&weight);
+            return;
+        }
+
+        if self.counters.len() < self.capacity {
+            self.counters.insert(val, (weight, R::zero()));
+            return;
+        }
+
+        // Replace the value with the smallest count.
+        let min = self
+            .counters
+            .iter()
+            .min_by(|(_, (x, _)), (_, (y, _))| x.cmp(y))
+            .map(|(val, (count, _))| (val.clone(), count.clone()));
+        if let Some((min_val, min_count)) = min {
+            self.counters.remove(&min_val);
+            let mut count = min_count.clone();
+            count.add_assign_by_ref(&weight);
+            self.counters.insert(val, (count, min_count));
+        }
+    }
+
+    /// Returns up to `k` tracked values with the largest counts as
+    /// `(value, count, error)`, ordered by decreasing count, with ties broken
+    /// by value.
+    fn top(&self, k: usize) -> TopK<V, R> {
+        let mut top: Vec<_> = self
+            .counters
+            .iter()
+            .map(|(val, (count, error))| (val.clone(), count.clone(), error.clone()))
+            .collect();
+        top.sort_by(|(x, x_count, _), (y, y_count, _)| y_count.cmp(x_count).then(x.cmp(y)));
+        top.truncate(k);
+        top
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        indexed_zset,
+        trace::{BatchReader, Cursor},
+        OrdIndexedZSet, RootCircuit, Runtime,
+    };
+    use std::sync::{Arc, Mutex};
+
+    type ApproxOutput = OrdIndexedZSet<usize, (usize, (isize, isize)), isize>;
+
+    fn approx_topk_test(workers: usize) {
+        let output: Arc<Mutex<ApproxOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, usize, isize>();
+            input_stream
+                .approx_topk(3, 20)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        // Returns the integrated output as `(key, value, count, error)`,
+        // ordered by key and decreasing count.
+        let current_top = || {
+            let output = output_clone.lock().unwrap();
+            let mut cursor = output.cursor();
+            let mut top = Vec::new();
+            while cursor.key_valid() {
+                while cursor.val_valid() {
+                    // Previous estimates have been retracted.
+                    assert_eq!(cursor.weight(), 1);
+                    let (val, (count, error)) = *cursor.val();
+                    top.push((*cursor.key(), val, count, error));
+                    cursor.step_val();
+                }
+                cursor.step_key();
+            }
+            top.sort_by_key(|&(key, _, count, _)| (key, -count));
+            top
+        };
+
+        // Heavy hitters `1`, `2` and `3` in group `1`, mixed with a long tail of
+        // values that occur once each.
+        let mut tail = 1000;
+        for step in 1..=10 {
+            let mut updates = vec![(1, (1, 10)), (1, (2, 5)), (1, (3, 3)), (2, (7, 1))];
+            for _ in 0..20 {
+                updates.push((1, (tail, 1)));
+                tail += 1;
+            }
+            input_handle.append(&mut updates);
+            dbsp.step().unwrap();
+
+            // The integrated output contains exactly the latest estimate.
+            let top = current_top();
+            assert_eq!(top.len(), 4);
+            assert_eq!(top[3], (2, 7, step, 0));
+        }
+
+        let top = current_top();
+
+        let true_counts = [(1, 100), (2, 50), (3, 30)];
+        assert_eq!(top.len(), 4);
+        for ((key, val, count, error), (expected_val, true_count)) in
+            top[..3].iter().zip(true_counts)
+        {
+            assert_eq!((*key, *val), (1, expected_val));
+            // Estimates never undercount, and `count - error` never
+            // overcounts.
+            assert!(*count >= true_count, "{val}: {count} < {true_count}");
+            assert!(count - error <= true_count, "{val}: {count} - {error}");
+            // The error is at most `n / capacity`.
+            assert!(*error <= 380 / 20);
+        }
+        // Groups are tracked separately.
+        assert_eq!(top[3], (2, 7, 10, 0));
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn approx_topk_test1() {
+        approx_topk_test(1);
+    }
+
+    #[test]
+    fn approx_topk_test4() {
+        approx_topk_test(4);
+    }
+
+    #[test]
+    #[should_panic(expected = "approx_topk capacity (2) must be at least k (3)")]
+    fn approx_topk_invalid_capacity() {
+        let _ = RootCircuit::build(move |circuit| {
+            let (input_stream, _input_handle) =
+                circuit.add_input_indexed_zset::<usize, usize, isize>();
+            input_stream.approx_topk(3, 2);
+        });
+    }
+}
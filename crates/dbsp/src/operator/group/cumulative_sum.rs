@@ -0,0 +1,402 @@
+use crate::{
+    algebra::{
+        AddAssignByRef, HasZero, IndexedZSet, MulByRef, NegByRef, UnimplementedSemigroup,
+        ZRingValue,
+    },
+    operator::{group::flatten_groups, Fold},
+    trace::{Batch, BatchReader, Cursor},
+    DBData, OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::{cmp::Ordering, collections::BTreeMap};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Computes the cumulative sum of `column` within each group.
+    ///
+    /// For every value `v` in a group, visited in ascending order, the
+    /// operator outputs `(v, sum)`, with the weight of `v`, where `sum` is
+    /// the sum of `column(u) * w` over all values `u <= v` in the group with
+    /// weights `w`.  Values whose weights are zero or negative are skipped.
+    /// This matches the semantics of SQL `SUM(column) OVER (ORDER BY v)`.
+    ///
+    /// Inserting or retracting a value changes the sum of all values after
+    /// it, so the operator re-evaluates modified groups in full.  See
+    /// [`cumulative_sum_incremental`](`Self::cumulative_sum_incremental`) for
+    /// a variant that handles appends to large groups more efficiently.
+    pub fn cumulative_sum<F, X>(
+        &self,
+        column: F,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, X), B::R>>
+    where
+        F: Fn(&B::Val) -> X + Clone + 'static,
+        X: DBData + From<B::R> + MulByRef<Output = X> + AddAssignByRef + HasZero,
+    {
+        let sums = self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+            Vec::new(),
+            move |acc: &mut Vec<((B::Val, X), B::R)>, v: &B::Val, w: B::R| {
+                if w.le0() {
+                    return;
+                }
+                let mut sum = match acc.last() {
+                    Some(((_, sum), _)) => sum.clone(),
+                    None => X::zero(),
+                };
+                sum.add_assign_by_ref(&column(v).mul_by_ref(&X::from(w.clone())));
+                acc.push(((v.clone(), sum), w));
+            },
+        ));
+
+        flatten_groups(&sums)
+    }
+
+    /// Computes the cumulative sum of `column` within each group, extending
+    /// the sums of groups that are only appended to instead of recomputing
+    /// them.
+    ///
+    /// The output is the same as that of
+    /// [`cumulative_sum`](`Self::cumulative_sum`).  The operator keeps the
+    /// contents of every group in memory, along with the running total at
+    /// each value.  When all changes
+    /// to a group in an input batch are insertions of values larger than any
+    /// value already in the group, it extends the running total of the group,
+    /// doing constant work per inserted value.  Any other change, i.e., an
+    /// insertion in the interior of the group, a retraction or a change in
+    /// the weight of an existing value, is handled by recomputing the sums of
+    /// all values from the first modified one to the end of the group.
+    pub fn cumulative_sum_incremental<F, X>(
+        &self,
+        column: F,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, X), B::R>>
+    where
+        F: Fn(&B::Val) -> X + 'static,
+        X: DBData + From<B::R> + MulByRef<Output = X> + AddAssignByRef + HasZero,
+    {
+        let mut groups: BTreeMap<B::Key, CumulativeSumGroup<B::Val, X, B::R>> = BTreeMap::new();
+
+        self.shard()
+            .apply_named("CumulativeSumIncremental", move |delta: &B| {
+                let mut tuples = Vec::new();
+                let mut updates = Vec::new();
+                let mut cursor = delta.cursor();
+
+                while cursor.key_valid() {
+                    while cursor.val_valid() {
+                        let weight = cursor.weight();
+                        if !weight.is_zero() {
+                            updates.push((cursor.val().clone(), weight));
+                        }
+                        cursor.step_val();
+                    }
+
+                    if !updates.is_empty() {
+                        let key = cursor.key();
+                        let group = groups
+                            .entry(key.clone())
+                            .or_insert_with(CumulativeSumGroup::new);
+                        group.update(&mut updates, &column, |val, sum, weight| {
+                            tuples.push(((key.clone(), (val, sum)), weight))
+                        });
+                        if group.rows.is_empty() {
+                            groups.remove(key);
+                        }
+                    }
+
+                    cursor.step_key();
+                }
+
+                OrdIndexedZSet::from_tuples((), tuples)
+            })
+            .mark_sharded()
+    }
+}
+
+/// State of a single group of
+/// [`cumulative_sum_incremental`](`Stream::cumulative_sum_incremental`).
+struct CumulativeSumGroup<V, X, R> {
+    /// Contents of the group, `(value, weight, sum)`, in ascending order of
+    /// values, with zero weights removed.  `sum` is the cumulative sum up to
+    /// and including `value`; rows with positive weights are output.
+    rows: Vec<(V, R, X)>,
+}
+
+impl<V, X, R> CumulativeSumGroup<V, X, R>
+where
+    V: DBData,
+    X: DBData + From<R> + MulByRef<Output = X> + AddAssignByRef + HasZero,
+    R: ZRingValue,
+{
+    fn new() -> Self {
+        Self { rows: Vec::new() }
+    }
+
+    /// Applies `updates`, given as `(value, weight)` pairs with non-zero
+    /// weights in ascending order of values, to the group, reporting changes
+    /// to the output via `emit(value, sum, weight)`.  Drains `updates`.
+    fn update<F, E>(&mut self, updates: &mut Vec<(V, R)>, column: &F, mut emit: E)
+    where
+        F: Fn(&V) -> X,
+        E: FnMut(V, X, R),
+    {
+        let appends_only = updates.iter().all(|(_, weight)| weight.ge0())
+            && match self.rows.last() {
+                Some((last, _, _)) => &updates[0].0 > last,
+                None => true,
+            };
+
+        if appends_only {
+            // Sums of existing values are unaffected: extend the running total.
+            let mut sum = self.sum_before(self.rows.len());
+            for (val, weight) in updates.drain(..) {
+                sum.add_assign_by_ref(&column(&val).mul_by_ref(&X::from(weight.clone())));
+                emit(val.clone(), sum.clone(), weight.clone());
+                self.rows.push((val, weight, sum.clone()));
+            }
+            return;
+        }
+
+        // Sums before the first modified value are unaffected, recompute the
+        // rest of the group.
+        let start = self.rows.partition_point(|(val, _, _)| val < &updates[0].0);
+        let tail: Vec<_> = self.rows.drain(start..).collect();
+        for (val, weight, sum) in tail.iter() {
+            if !weight.le0() {
+                emit(val.clone(), sum.clone(), weight.neg_by_ref());
+            }
+        }
+
+        let mut sum = self.sum_before(start);
+        let mut tail = tail.into_iter().peekable();
+        let mut updates = updates.drain(..).peekable();
+        loop {
+            let (val, weight) = match (tail.peek(), updates.peek()) {
+                (Some((old_val, _, _)), Some((new_val, _))) => match old_val.cmp(new_val) {
+                    Ordering::Less => {
+                        let (val, weight, _) = tail.next().unwrap();
+                        (val, weight)
+                    }
+                    Ordering::Greater => updates.next().unwrap(),
+                    Ordering::Equal => {
+                        let (val, mut weight, _) = tail.next().unwrap();
+                        weight.add_assign_by_ref(&updates.next().unwrap().1);
+                        (val, weight)
+                    }
+                },
+                (Some(_), None) => {
+                    let (val, weight, _) = tail.next().unwrap();
+                    (val, weight)
+                }
+                (None, Some(_)) => updates.next().unwrap(),
+                (None, None) => break,
+            };
+
+            if weight.is_zero() {
+                continue;
+            }
+            if !weight.le0() {
+                sum.add_assign_by_ref(&column(&val).mul_by_ref(&X::from(weight.clone())));
+                emit(val.clone(), sum.clone(), weight.clone());
+            }
+            self.rows.push((val, weight, sum.clone()));
+        }
+    }
+
+    /// Returns the sum of the first `len` rows.
+    fn sum_before(&self, len: usize) -> X {
+        match len.checked_sub(1) {
+            Some(last) => self.rows[last].2.clone(),
+            None => X::zero(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        indexed_zset,
+        operator::group::tester::{group_updates, GroupUpdate},
+        CollectionHandle, DBSPHandle, OrdIndexedZSet, Runtime,
+    };
+    use proptest::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    type SumOutput = Arc<Mutex<OrdIndexedZSet<usize, (usize, isize), isize>>>;
+
+    /// Builds a circuit that computes both the full and the incremental
+    /// cumulative sums of its input.
+    fn cumulative_sum_circuit(
+        workers: usize,
+    ) -> (
+        DBSPHandle,
+        CollectionHandle<usize, (usize, isize)>,
+        SumOutput,
+        SumOutput,
+    ) {
+        let full_output: SumOutput = Arc::new(Mutex::new(indexed_zset! {}));
+        let incremental_output: SumOutput = Arc::new(Mutex::new(indexed_zset! {}));
+        let full_output_clone = full_output.clone();
+        let incremental_output_clone = incremental_output.clone();
+
+        let (dbsp, input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, usize, isize>();
+            input_stream
+                .cumulative_sum(|&v| v as isize)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *full_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_stream
+                .cumulative_sum_incremental(|&v| v as isize)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *incremental_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        (
+            dbsp,
+            input_handle,
+            full_output_clone,
+            incremental_output_clone,
+        )
+    }
+
+    fn cumulative_sum_test(workers: usize) {
+        let (mut dbsp, mut input_handle, full_output, incremental_output) =
+            cumulative_sum_circuit(workers);
+
+        input_handle.append(&mut vec![(1, (1, 1)), (1, (2, 2)), (2, (5, 1))]);
+        dbsp.step().unwrap();
+        let expected = indexed_zset! {
+            1 => {(1, 1) => 1, (2, 5) => 2},
+            2 => {(5, 5) => 1}
+        };
+        assert_eq!(&*full_output.lock().unwrap(), &expected);
+        assert_eq!(&*incremental_output.lock().unwrap(), &expected);
+
+        // Append-only inserts extend the running total.
+        input_handle.append(&mut vec![(1, (4, 1)), (1, (6, 1)), (2, (7, 3))]);
+        dbsp.step().unwrap();
+        let expected = indexed_zset! {
+            1 => {(1, 1) => 1, (2, 5) => 2, (4, 9) => 1, (6, 15) => 1},
+            2 => {(5, 5) => 1, (7, 26) => 3}
+        };
+        assert_eq!(&*full_output.lock().unwrap(), &expected);
+        assert_eq!(&*incremental_output.lock().unwrap(), &expected);
+
+        // An insert in the interior of the group changes the sums of all
+        // values after it.
+        input_handle.append(&mut vec![(1, (3, 1))]);
+        dbsp.step().unwrap();
+        let expected = indexed_zset! {
+            1 => {(1, 1) => 1, (2, 5) => 2, (3, 8) => 1, (4, 12) => 1, (6, 18) => 1},
+            2 => {(5, 5) => 1, (7, 26) => 3}
+        };
+        assert_eq!(&*full_output.lock().unwrap(), &expected);
+        assert_eq!(&*incremental_output.lock().unwrap(), &expected);
+
+        // So does a retraction.
+        input_handle.append(&mut vec![(1, (2, -2)), (2, (7, -3))]);
+        dbsp.step().unwrap();
+        let expected = indexed_zset! {
+            1 => {(1, 1) => 1, (3, 4) => 1, (4, 8) => 1, (6, 14) => 1},
+            2 => {(5, 5) => 1}
+        };
+        assert_eq!(&*full_output.lock().unwrap(), &expected);
+        assert_eq!(&*incremental_output.lock().unwrap(), &expected);
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn cumulative_sum_test1() {
+        cumulative_sum_test(1);
+    }
+
+    #[test]
+    fn cumulative_sum_test4() {
+        cumulative_sum_test(4);
+    }
+
+    /// Checks that the incremental and the full cumulative sums agree after
+    /// every step.
+    fn cumulative_sum_equivalence_test(workers: usize, steps: &[Vec<GroupUpdate>]) {
+        let (mut dbsp, mut input_handle, full_output, incremental_output) =
+            cumulative_sum_circuit(workers);
+
+        for updates in steps {
+            let mut tuples: Vec<_> = updates.iter().map(GroupUpdate::as_tuple).collect();
+            input_handle.append(&mut tuples);
+            dbsp.step().unwrap();
+
+            assert_eq!(
+                &*incremental_output.lock().unwrap(),
+                &*full_output.lock().unwrap()
+            );
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    /// Updates that only ever append values larger than any value inserted
+    /// before them to each group.
+    fn append_only_updates() -> impl Strategy<Value = Vec<Vec<GroupUpdate>>> {
+        prop::collection::vec(
+            prop::collection::vec((0..4usize, 1..4usize, 1..3isize), 0..8),
+            0..16,
+        )
+        .prop_map(|steps| {
+            let mut next = [0; 4];
+            steps
+                .into_iter()
+                .map(|step| {
+                    step.into_iter()
+                        .map(|(key, gap, weight)| {
+                            next[key] += gap;
+                            GroupUpdate::Insert {
+                                key,
+                                val: next[key],
+                                weight,
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn cumulative_sum_append_only_test1(steps in append_only_updates()) {
+            cumulative_sum_equivalence_test(1, &steps);
+        }
+
+        #[test]
+        fn cumulative_sum_append_only_test4(steps in append_only_updates()) {
+            cumulative_sum_equivalence_test(4, &steps);
+        }
+
+        #[test]
+        fn cumulative_sum_equivalence_test1(steps in group_updates()) {
+            cumulative_sum_equivalence_test(1, &steps);
+        }
+
+        #[test]
+        fn cumulative_sum_equivalence_test4(steps in group_updates()) {
+            cumulative_sum_equivalence_test(4, &steps);
+        }
+    }
+}
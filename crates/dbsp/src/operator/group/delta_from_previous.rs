@@ -0,0 +1,156 @@
+use crate::{
+    algebra::{AddByRef, GroupValue, HasOne, HasZero, IndexedZSet, NegByRef, ZRingValue},
+    trace::{Batch, BatchReader, Cursor},
+    OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::collections::BTreeMap;
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+    B::Val: GroupValue,
+{
+    /// Converts a stream of changes to per-key aggregates into a stream of
+    /// differences between consecutive aggregate values.
+    ///
+    /// The input stream is expected to carry changes to an indexed Z-set
+    /// that maps each key to at most one value, such as the output of
+    /// [`aggregate`](`Self::aggregate`): when the aggregate of a key changes
+    /// from `old` to `new`, the input batch retracts `(key, old)` and inserts
+    /// `(key, new)`.
+    ///
+    /// The operator remembers the last value of every key.  For each key
+    /// modified by the input batch, it outputs `(key, new - old)` with weight
+    /// `1`, where a missing `old` or `new` value, i.e., a key that is
+    /// inserted for the first time or deleted, counts as zero.  Changes that
+    /// leave the value of a key unchanged produce no output.
+    pub fn delta_from_previous(&self) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        let mut previous: BTreeMap<B::Key, B::Val> = BTreeMap::new();
+
+        self.shard()
+            .apply_named("DeltaFromPrevious", move |delta: &B| {
+                let mut tuples = Vec::new();
+                let mut cursor = delta.cursor();
+
+                while cursor.key_valid() {
+                    let mut new = None;
+                    while cursor.val_valid() {
+                        if cursor.weight().ge0() {
+                            new = Some(cursor.val().clone());
+                        }
+                        cursor.step_val();
+                    }
+
+                    let key = cursor.key();
+                    let old = match new {
+                        Some(new) => previous.insert(key.clone(), new),
+                        None => previous.remove(key),
+                    };
+                    let new = previous.get(key);
+
+                    let diff = match (new, old) {
+                        (Some(new), Some(old)) => new.add_by_ref(&old.neg_by_ref()),
+                        (Some(new), None) => new.clone(),
+                        (None, Some(old)) => old.neg_by_ref(),
+                        (None, None) => B::Val::zero(),
+                    };
+                    if !diff.is_zero() {
+                        tuples.push(((key.clone(), diff), B::R::one()));
+                    }
+
+                    cursor.step_key();
+                }
+
+                OrdIndexedZSet::from_tuples((), tuples)
+            })
+            .mark_sharded()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, OrdIndexedZSet, Runtime};
+    use std::sync::{Arc, Mutex};
+
+    fn delta_from_previous_test(workers: usize) {
+        let output: Arc<Mutex<OrdIndexedZSet<usize, isize, isize>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, isize, isize>();
+            input_stream
+                .delta_from_previous()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        // New keys are reported relative to zero.
+        input_handle.append(&mut vec![(1, (10, 1)), (2, (5, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            *output_clone.lock().unwrap(),
+            indexed_zset! {1 => {10 => 1}, 2 => {5 => 1}}
+        );
+
+        // Updates replace the previous value of the key.
+        input_handle.append(&mut vec![
+            (1, (10, -1)),
+            (1, (15, 1)),
+            (2, (5, -1)),
+            (2, (2, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            *output_clone.lock().unwrap(),
+            indexed_zset! {1 => {5 => 1}, 2 => {-3 => 1}}
+        );
+
+        // Steps without input produce no output.
+        dbsp.step().unwrap();
+        assert_eq!(*output_clone.lock().unwrap(), indexed_zset! {});
+
+        // Keys whose value doesn't change are suppressed.
+        input_handle.append(&mut vec![
+            (1, (15, -1)),
+            (1, (15, 1)),
+            (2, (2, -1)),
+            (2, (7, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(*output_clone.lock().unwrap(), indexed_zset! {2 => {5 => 1}});
+
+        // Deleting a key reports the negation of its last value, and
+        // reinserting it starts over from zero.
+        input_handle.append(&mut vec![(1, (15, -1)), (3, (4, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            *output_clone.lock().unwrap(),
+            indexed_zset! {1 => {-15 => 1}, 3 => {4 => 1}}
+        );
+
+        input_handle.append(&mut vec![(1, (3, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(*output_clone.lock().unwrap(), indexed_zset! {1 => {3 => 1}});
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn delta_from_previous_test1() {
+        delta_from_previous_test(1);
+    }
+
+    #[test]
+    fn delta_from_previous_test4() {
+        delta_from_previous_test(4);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    algebra::{IndexedZSet, UnimplementedSemigroup, ZRingValue},
+    operator::{FilterMap, Fold},
+    OrdIndexedZSet, RootCircuit, Stream,
+};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Reduces each group to its distinct values in ascending order.
+    ///
+    /// For every key in the input, the operator walks the values of the
+    /// group in sorted order and emits each value whose total weight is
+    /// positive exactly once, with weight `1`.  Values whose weights sum up
+    /// to zero or less are dropped.
+    ///
+    /// Unlike [`distinct`](`Self::distinct`), which tests each
+    /// `(key, value)` tuple independently, this operator processes the
+    /// whole group at once, guaranteeing that distinct values are
+    /// produced in the order of their first occurrence in the sorted group.
+    /// This matches the semantics of SQL `SELECT DISTINCT ... ORDER BY`.
+    pub fn distinct_ordered(&self) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+            Vec::new(),
+            |acc: &mut Vec<B::Val>, v: &B::Val, w: B::R| {
+                if w.ge0() {
+                    acc.push(v.clone());
+                }
+            },
+        ))
+        .flat_map_index(|(k, vals)| {
+            let k = k.clone();
+            vals.clone().into_iter().map(move |v| (k.clone(), v))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, OrdIndexedZSet, Runtime};
+    use std::sync::{Arc, Mutex};
+
+    fn distinct_ordered_test(workers: usize) {
+        let output: Arc<Mutex<OrdIndexedZSet<usize, usize, isize>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) = circuit.add_input_indexed_zset();
+            input_stream
+                .distinct_ordered()
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, (3, 2)),
+            (1, (1, 5)),
+            (1, (2, -1)),
+            (1, (3, 1)),
+            (2, (7, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {1 => 1, 3 => 1}, 2 => {7 => 1}}
+        );
+
+        // Value `2` becomes positive, value `1` is retracted completely.
+        input_handle.append(&mut vec![(1, (2, 3)), (1, (1, -5))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 1, 3 => 1}, 2 => {7 => 1}}
+        );
+
+        // Retract the last value of group `2`.
+        input_handle.append(&mut vec![(2, (7, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 1, 3 => 1}}
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn distinct_ordered_test1() {
+        distinct_ordered_test(1);
+    }
+
+    #[test]
+    fn distinct_ordered_test4() {
+        distinct_ordered_test(4);
+    }
+}
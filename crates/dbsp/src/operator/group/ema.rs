@@ -0,0 +1,160 @@
+use crate::{
+    algebra::{IndexedZSet, UnimplementedSemigroup, ZRingValue, F64},
+    operator::{group::flatten_groups, Fold},
+    OrdIndexedZSet, RootCircuit, Stream,
+};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Computes the exponential moving average of the values in each group.
+    ///
+    /// For every value `x` in a group, visited in ascending order, the
+    /// operator outputs `(x, ema)`, with the weight of `x`, where
+    /// `ema = alpha * x + (1 - alpha) * prev_ema` and `prev_ema` is the
+    /// average computed for the previous value.  The average of the first
+    /// value in the group is the value itself.  Values whose weights are zero
+    /// or negative are skipped, and each remaining value is applied to the
+    /// average once, regardless of its weight.
+    ///
+    /// The average depends on the order of values, so inserting or retracting
+    /// a value changes the output for all values after it.  The operator
+    /// therefore re-evaluates modified groups in full rather than updating
+    /// them incrementally.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0 < alpha <= 1`.
+    pub fn ema(
+        &self,
+        alpha: f64,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, F64), B::R>>
+    where
+        B::Val: Into<f64>,
+    {
+        assert!(
+            alpha > 0.0 && alpha <= 1.0,
+            "EMA smoothing factor must be in (0, 1]: {alpha}"
+        );
+
+        let ema = self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+            Vec::new(),
+            move |acc: &mut Vec<((B::Val, F64), B::R)>, v: &B::Val, w: B::R| {
+                if w.le0() {
+                    return;
+                }
+                let x: f64 = v.clone().into();
+                let ema = match acc.last() {
+                    Some(((_, prev), _)) => alpha * x + (1.0 - alpha) * prev.into_inner(),
+                    None => x,
+                };
+                acc.push(((v.clone(), F64::new(ema)), w));
+            },
+        ));
+
+        flatten_groups(&ema)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{algebra::F64, indexed_zset, trace::Batch, OrdIndexedZSet, RootCircuit, Runtime};
+    use std::sync::{Arc, Mutex};
+
+    type EmaOutput = OrdIndexedZSet<usize, (i32, F64), isize>;
+
+    /// Reference implementation: EMA of `values`, listed in ascending order.
+    fn expected_ema(key: usize, values: &[i32], alpha: f64) -> Vec<((usize, (i32, F64)), isize)> {
+        let mut result = Vec::new();
+        let mut prev: Option<f64> = None;
+        for &x in values {
+            let ema = match prev {
+                Some(prev) => alpha * x as f64 + (1.0 - alpha) * prev,
+                None => x as f64,
+            };
+            result.push(((key, (x, F64::new(ema))), 1));
+            prev = Some(ema);
+        }
+        result
+    }
+
+    fn ema_test(workers: usize) {
+        let output: Arc<Mutex<EmaOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, i32, isize>();
+            input_stream
+                .ema(0.25)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, (30, 1)),
+            (1, (10, 1)),
+            (1, (20, 1)),
+            (1, (40, 1)),
+            (2, (8, 1)),
+            (2, (4, 1)),
+        ]);
+        dbsp.step().unwrap();
+        let mut expected = expected_ema(1, &[10, 20, 30, 40], 0.25);
+        expected.extend(expected_ema(2, &[4, 8], 0.25));
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &EmaOutput::from_tuples((), expected)
+        );
+        // Spot-check the reference implementation.
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {(10, F64::new(10.0)) => 1, (20, F64::new(12.5)) => 1, (30, F64::new(16.875)) => 1, (40, F64::new(22.65625)) => 1},
+                2 => {(4, F64::new(4.0)) => 1, (8, F64::new(5.0)) => 1}
+            }
+        );
+
+        // Retracting the first value of group `1` changes the average of
+        // every value after it.
+        input_handle.append(&mut vec![(1, (10, -1)), (2, (6, 1))]);
+        dbsp.step().unwrap();
+        let mut expected = expected_ema(1, &[20, 30, 40], 0.25);
+        expected.extend(expected_ema(2, &[4, 6, 8], 0.25));
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &EmaOutput::from_tuples((), expected)
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn ema_test1() {
+        ema_test(1);
+    }
+
+    #[test]
+    fn ema_test4() {
+        ema_test(4);
+    }
+
+    #[test]
+    #[should_panic(expected = "EMA smoothing factor must be in (0, 1]")]
+    fn ema_invalid_alpha() {
+        let _ = RootCircuit::build(move |circuit| {
+            let (input_stream, _input_handle) =
+                circuit.add_input_indexed_zset::<usize, i32, isize>();
+            input_stream.ema(0.0);
+        });
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{
+    algebra::{AddAssignByRef, HasOne, HasZero, IndexedZSet, UnimplementedSemigroup, ZRingValue},
+    operator::{group::flatten_groups, Fold},
+    OrdIndexedZSet, RootCircuit, Stream,
+};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Computes a histogram of the values in each group.
+    ///
+    /// `bins` contains the edges of the histogram bins in ascending order.
+    /// For `n` edges, there are `n + 1` bins: bin `0` counts values below
+    /// `bins[0]`, bin `i` for `0 < i < n` counts values in the half-open
+    /// range `[bins[i - 1], bins[i])`, and bin `n` counts values greater than
+    /// or equal to `bins[n - 1]`.
+    ///
+    /// For each key in the input, the operator outputs a `(bin, count)` pair
+    /// for every bin whose count, i.e., the sum of the weights of the values
+    /// in the bin, is non-zero, with weight `1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bins` is empty or isn't sorted in strictly ascending order.
+    pub fn histogram(
+        &self,
+        bins: Vec<f64>,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (usize, B::R), B::R>>
+    where
+        B::Val: Into<f64>,
+    {
+        assert!(!bins.is_empty(), "histogram requires at least one bin edge");
+        assert!(
+            !bins[0].is_nan() && bins.windows(2).all(|edges| edges[0] < edges[1]),
+            "histogram bin edges must be sorted in strictly ascending order: {bins:?}"
+        );
+
+        let num_bins = bins.len() + 1;
+        let histogram = self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+            vec![B::R::zero(); num_bins],
+            move |counts: &mut Vec<B::R>, v: &B::Val, w: B::R| {
+                let v: f64 = v.clone().into();
+                let bin = bins.partition_point(|edge| *edge <= v);
+                counts[bin].add_assign_by_ref(&w);
+            },
+            |counts: Vec<B::R>| {
+                counts
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, count)| !count.is_zero())
+                    .map(|bin| (bin, B::R::one()))
+                    .collect::<Vec<_>>()
+            },
+        ));
+
+        flatten_groups(&histogram)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, OrdIndexedZSet, RootCircuit, Runtime};
+    use std::sync::{Arc, Mutex};
+
+    fn histogram_test(workers: usize) {
+        let output: Arc<Mutex<OrdIndexedZSet<usize, (usize, isize), isize>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, i32, isize>();
+            input_stream
+                .histogram(vec![10.0, 20.0, 50.0])
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        // Latencies in group `1`: one underflow, three in `[10, 20)`, two in
+        // `[20, 50)`, and one overflow.  Edges belong to the bin above them.
+        input_handle.append(&mut vec![
+            (1, (5, 1)),
+            (1, (10, 1)),
+            (1, (15, 2)),
+            (1, (20, 1)),
+            (1, (49, 1)),
+            (1, (50, 1)),
+            (2, (100, 3)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {(0, 1) => 1, (1, 3) => 1, (2, 2) => 1, (3, 1) => 1},
+                2 => {(3, 3) => 1}
+            }
+        );
+
+        // Emptying a bin removes it from the output.
+        input_handle.append(&mut vec![(1, (5, -1)), (1, (30, 1)), (2, (100, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {(1, 3) => 1, (2, 3) => 1, (3, 1) => 1},
+                2 => {(3, 2) => 1}
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn histogram_test1() {
+        histogram_test(1);
+    }
+
+    #[test]
+    fn histogram_test4() {
+        histogram_test(4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn histogram_unsorted_bins() {
+        RootCircuit::build(|circuit| {
+            let (input_stream, _input_handle) =
+                circuit.add_input_indexed_zset::<usize, i32, isize>();
+            input_stream.histogram(vec![10.0, 5.0]);
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    algebra::{IndexedZSet, UnimplementedSemigroup, ZRingValue},
+    operator::{group::flatten_groups, Fold},
+    OrdIndexedZSet, RootCircuit, Stream,
+};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Pairs each value in a group with the value `offset` positions before
+    /// it.
+    ///
+    /// For every value `v` in a group, visited in ascending order, the
+    /// operator outputs `(v, lag)` with the weight of `v`, where `lag` is the
+    /// value `offset` positions earlier in the group, or `None` if there is no
+    /// such value.  This matches the semantics of SQL
+    /// `LAG(v, offset) OVER (ORDER BY v)`.
+    ///
+    /// Each distinct value counts as a single position, regardless of its
+    /// weight.  Values whose weights are zero or negative are skipped.
+    pub fn lag(
+        &self,
+        offset: usize,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, Option<B::Val>), B::R>> {
+        self.shift(offset, false)
+    }
+
+    /// Pairs each value in a group with the value `offset` positions after
+    /// it.
+    ///
+    /// This is the dual of [`lag`](`Self::lag`): the second component of each
+    /// output tuple is the value `offset` positions later in the group, or
+    /// `None` if there is no such value, matching SQL
+    /// `LEAD(v, offset) OVER (ORDER BY v)`.
+    pub fn lead(
+        &self,
+        offset: usize,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, Option<B::Val>), B::R>> {
+        self.shift(offset, true)
+    }
+
+    /// Materializes each group as a sorted vector and pairs every value with
+    /// the value `offset` positions after it if `forward` is `true`, or
+    /// before it otherwise.
+    fn shift(
+        &self,
+        offset: usize,
+        forward: bool,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, Option<B::Val>), B::R>> {
+        let shifted = self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+            Vec::new(),
+            |vals: &mut Vec<(B::Val, B::R)>, v: &B::Val, w: B::R| {
+                if !w.le0() {
+                    vals.push((v.clone(), w));
+                }
+            },
+            move |vals: Vec<(B::Val, B::R)>| {
+                vals.iter()
+                    .enumerate()
+                    .map(|(i, (v, w))| {
+                        let other = if forward {
+                            i.checked_add(offset)
+                        } else {
+                            i.checked_sub(offset)
+                        };
+                        let other = other.and_then(|j| vals.get(j)).map(|(v, _)| v.clone());
+                        ((v.clone(), other), w.clone())
+                    })
+                    .collect::<Vec<_>>()
+            },
+        ));
+
+        flatten_groups(&shifted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        indexed_zset,
+        operator::{
+            group::tester::{group_updates, GroupTransformTester, GroupUpdate},
+            FilterMap,
+        },
+        OrdIndexedZSet, RootCircuit, Runtime, Stream,
+    };
+    use proptest::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    type ShiftOutput = OrdIndexedZSet<usize, (usize, Option<usize>), isize>;
+
+    fn lag_lead_test(workers: usize) {
+        let lag_output: Arc<Mutex<ShiftOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let lead_output: Arc<Mutex<ShiftOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let lag_output_clone = lag_output.clone();
+        let lead_output_clone = lead_output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, usize, isize>();
+            input_stream
+                .lag(1)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *lag_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_stream
+                .lead(1)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *lead_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, (10, 1)),
+            (1, (20, 1)),
+            (1, (30, 2)),
+            (1, (40, 1)),
+            (1, (50, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*lag_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {
+                    (10, None) => 1,
+                    (20, Some(10)) => 1,
+                    (30, Some(20)) => 2,
+                    (40, Some(30)) => 1,
+                    (50, Some(40)) => 1
+                }
+            }
+        );
+        assert_eq!(
+            &*lead_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {
+                    (10, Some(20)) => 1,
+                    (20, Some(30)) => 1,
+                    (30, Some(40)) => 2,
+                    (40, Some(50)) => 1,
+                    (50, None) => 1
+                }
+            }
+        );
+
+        // Removing the middle value links its neighbors.
+        input_handle.append(&mut vec![(1, (30, -2))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*lag_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {(10, None) => 1, (20, Some(10)) => 1, (40, Some(20)) => 1, (50, Some(40)) => 1}
+            }
+        );
+        assert_eq!(
+            &*lead_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {(10, Some(20)) => 1, (20, Some(40)) => 1, (40, Some(50)) => 1, (50, None) => 1}
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn lag_lead_test1() {
+        lag_lead_test(1);
+    }
+
+    #[test]
+    fn lag_lead_test4() {
+        lag_lead_test(4);
+    }
+
+    type TestStream = Stream<RootCircuit, OrdIndexedZSet<usize, usize, isize>>;
+
+    /// Encodes the output of `lag`/`lead` as a single `usize`, so the operators
+    /// can be validated with [`GroupTransformTester`].
+    fn encode((v, other): (usize, Option<usize>)) -> usize {
+        v * 100 + other.unwrap_or(99)
+    }
+
+    /// Reference implementation of `lag` (`forward == false`) and `lead`
+    /// (`forward == true`) over the complete contents of a group.
+    fn shift_reference(
+        group: &[(usize, isize)],
+        offset: usize,
+        forward: bool,
+    ) -> Vec<(usize, isize)> {
+        let group: Vec<_> = group.iter().filter(|(_, w)| *w > 0).collect();
+        group
+            .iter()
+            .enumerate()
+            .map(|(i, &&(v, w))| {
+                let other = if forward {
+                    group.get(i + offset)
+                } else {
+                    i.checked_sub(offset).and_then(|j| group.get(j))
+                };
+                (encode((v, other.map(|&&(v, _)| v))), w)
+            })
+            .collect()
+    }
+
+    fn shift_incremental_test(
+        workers: usize,
+        offset: usize,
+        forward: bool,
+        steps: &[Vec<GroupUpdate>],
+    ) {
+        GroupTransformTester::new(
+            workers,
+            move |stream: &TestStream| {
+                let shifted = if forward {
+                    stream.lead(offset)
+                } else {
+                    stream.lag(offset)
+                };
+                shifted.map_index(|(k, v)| (*k, encode(*v)))
+            },
+            move |group: &[(usize, isize)]| shift_reference(group, offset, forward),
+        )
+        .run(steps);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn lag_incremental_test1(steps in group_updates(), offset in 0..4usize) {
+            shift_incremental_test(1, offset, false, &steps);
+        }
+
+        #[test]
+        fn lag_incremental_test4(steps in group_updates(), offset in 0..4usize) {
+            shift_incremental_test(4, offset, false, &steps);
+        }
+
+        #[test]
+        fn lead_incremental_test1(steps in group_updates(), offset in 0..4usize) {
+            shift_incremental_test(1, offset, true, &steps);
+        }
+
+        #[test]
+        fn lead_incremental_test4(steps in group_updates(), offset in 0..4usize) {
+            shift_incremental_test(4, offset, true, &steps);
+        }
+    }
+}
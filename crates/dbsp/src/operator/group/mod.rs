@@ -0,0 +1,243 @@
+//! Operators that transform each group of an indexed Z-set as a whole.
+//!
+//! A group is the set of `(value, weight)` pairs associated with a single key
+//! of an indexed Z-set.  Operators in this module compute an arbitrary
+//! function of the complete contents of a group, visiting values in
+//! ascending order, and emit a (possibly empty) set of output values for the
+//! same key.
+//!
+//! All operators here are incremental: they are built on top of
+//! [`Stream::aggregate`](`crate::Stream::aggregate`), which re-evaluates
+//! only the groups modified by each input delta and emits the difference
+//! between the old and the new output of the group.
+
+use crate::{
+    algebra::MulByRef,
+    trace::{Batch, BatchReader, Cursor},
+    DBData, DBWeight, OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+mod approx_topk;
+mod cumulative_sum;
+mod delta_from_previous;
+mod distinct_ordered;
+mod ema;
+mod histogram;
+mod lag;
+mod mode;
+mod pivot;
+mod running;
+#[cfg(test)]
+mod tester;
+mod topk;
+mod variance;
+mod weighted_avg;
+
+pub use topk::{ColumnExtractor, SortDir};
+
+/// Expands the output of a group aggregate into individual rows.
+///
+/// The input stream contains, for each key, a list of `(value, weight)`
+/// pairs computed by an aggregator.  The weight of each output row is the
+/// product of its weight in the list and the weight of the list itself, so
+/// that retracting an old list retracts all of its rows.
+///
+/// Values don't need to appear in the lists in any particular order, since
+/// the output batch is sorted when it is assembled.  Aggregators are
+/// therefore free to emit values in whatever order is convenient, e.g.,
+/// in the order of their weights rather than values.
+fn flatten_groups<K, V, R>(
+    stream: &Stream<RootCircuit, OrdIndexedZSet<K, Vec<(V, R)>, R>>,
+) -> Stream<RootCircuit, OrdIndexedZSet<K, V, R>>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight + MulByRef<Output = R>,
+{
+    let flattened = stream.apply_named(
+        "FlattenGroups",
+        |batch: &OrdIndexedZSet<K, Vec<(V, R)>, R>| {
+            let mut tuples = Vec::with_capacity(batch.len());
+            let mut cursor = batch.cursor();
+
+            while cursor.key_valid() {
+                while cursor.val_valid() {
+                    let weight = cursor.weight();
+                    for (val, w) in cursor.val().iter() {
+                        tuples.push(((cursor.key().clone(), val.clone()), w.mul_by_ref(&weight)));
+                    }
+                    cursor.step_val();
+                }
+                cursor.step_key();
+            }
+
+            OrdIndexedZSet::from_tuples((), tuples)
+        },
+    );
+
+    flattened.mark_sharded_if(stream);
+    flattened
+}
+
+/// Counters describing the work done by a group operator.
+///
+/// Counters are cumulative across steps and are updated by
+/// [`Stream::group_transform_stats`].  Clones share the same counters, so a
+/// single instance can collect totals across all workers of a circuit.
+#[derive(Clone, Debug, Default)]
+pub struct GroupTransformStats {
+    groups_seen: Arc<AtomicU64>,
+    rows_emitted: Arc<AtomicU64>,
+}
+
+impl GroupTransformStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Total number of groups whose output was modified.
+    pub fn groups_seen(&self) -> u64 {
+        self.groups_seen.load(Ordering::Relaxed)
+    }
+
+    /// Total number of output rows, including retractions of previously
+    /// emitted rows.
+    pub fn rows_emitted(&self) -> u64 {
+        self.rows_emitted.load(Ordering::Relaxed)
+    }
+
+    fn update<K, V, R>(&self, batch: &OrdIndexedZSet<K, V, R>)
+    where
+        K: DBData,
+        V: DBData,
+        R: DBWeight,
+    {
+        self.groups_seen
+            .fetch_add(batch.key_count() as u64, Ordering::Relaxed);
+        self.rows_emitted
+            .fetch_add(batch.len() as u64, Ordering::Relaxed);
+    }
+}
+
+impl<K, V, R> Stream<RootCircuit, OrdIndexedZSet<K, V, R>>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+{
+    /// Records statistics about the output of a group operator in `stats`.
+    ///
+    /// Returns the input stream unmodified.  Call this on the output of a
+    /// group operator such as [`topk_asc`](`Self::topk_asc`) to count the
+    /// groups it updated and the rows it emitted at each step.  Operators
+    /// don't collect any statistics unless this method is called.
+    pub fn group_transform_stats(&self, stats: &GroupTransformStats) -> Self {
+        let stats = stats.clone();
+        self.inspect(move |batch| stats.update(batch))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        flatten_groups,
+        tester::{group_updates, GroupTransformTester, GroupUpdate},
+        GroupTransformStats,
+    };
+    use crate::{
+        algebra::UnimplementedSemigroup, operator::Fold, OrdIndexedZSet, RootCircuit, Runtime,
+        Stream,
+    };
+    use proptest::prelude::*;
+
+    type TestStream = Stream<RootCircuit, OrdIndexedZSet<usize, usize, isize>>;
+
+    /// Outputs all values of each group, listing them in an order unrelated
+    /// to the order of values.
+    fn scrambled_identity(stream: &TestStream) -> TestStream {
+        let groups = stream.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+            Vec::new(),
+            |acc: &mut Vec<(usize, isize)>, v: &usize, w: isize| acc.push((*v, w)),
+            |mut acc: Vec<(usize, isize)>| {
+                acc.sort_by_key(|(v, _)| v.wrapping_mul(0x9e37_79b9) % 101);
+                acc
+            },
+        ));
+
+        flatten_groups(&groups)
+    }
+
+    fn group_transform_stats_test(workers: usize) {
+        let stats = GroupTransformStats::new();
+        let stats_clone = stats.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, usize, isize>();
+            input_stream.topk_asc(2).group_transform_stats(&stats_clone);
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, (1, 1)),
+            (1, (2, 1)),
+            (1, (3, 1)),
+            (2, (5, 1)),
+            (3, (7, 1)),
+            (3, (8, 2)),
+        ]);
+        dbsp.step().unwrap();
+        // Group 1: +1, +2; group 2: +5; group 3: +7, +8.
+        assert_eq!(stats.groups_seen(), 3);
+        assert_eq!(stats.rows_emitted(), 5);
+
+        input_handle.append(&mut vec![(1, (1, -1)), (2, (9, 1)), (3, (9, 1))]);
+        dbsp.step().unwrap();
+        // Group 1: -1, +3; group 2: +9; group 3 is unchanged.
+        assert_eq!(stats.groups_seen(), 5);
+        assert_eq!(stats.rows_emitted(), 8);
+
+        // Steps that don't modify any groups don't contribute.
+        dbsp.step().unwrap();
+        assert_eq!(stats.groups_seen(), 5);
+        assert_eq!(stats.rows_emitted(), 8);
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn group_transform_stats_test1() {
+        group_transform_stats_test(1);
+    }
+
+    #[test]
+    fn group_transform_stats_test4() {
+        group_transform_stats_test(4);
+    }
+
+    fn unordered_emission_test(workers: usize, steps: &[Vec<GroupUpdate>]) {
+        GroupTransformTester::new(workers, scrambled_identity, |group: &[(usize, isize)]| {
+            group.to_vec()
+        })
+        .run(steps);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn unordered_emission_test1(steps in group_updates()) {
+            unordered_emission_test(1, &steps);
+        }
+
+        #[test]
+        fn unordered_emission_test4(steps in group_updates()) {
+            unordered_emission_test(4, &steps);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    algebra::{IndexedZSet, UnimplementedSemigroup, ZRingValue},
+    operator::{FilterMap, Fold},
+    OrdIndexedZSet, RootCircuit, Stream,
+};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Computes the mode, i.e., the most frequent value, of each group.
+    ///
+    /// For each key in the input, the operator outputs the value with the
+    /// largest positive weight in the group, with weight `1`.  When several
+    /// values share the largest weight, the smallest of them is chosen.
+    /// Groups that don't contain any values with positive weights produce no
+    /// output.  This matches the semantics of SQL `MODE()`.
+    ///
+    /// The mode cannot be maintained incrementally, so every time a group is
+    /// modified, the operator scans the entire contents of the group to
+    /// recompute it.
+    pub fn mode_value(&self) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+            None,
+            |mode: &mut Option<(B::Val, B::R)>, v: &B::Val, w: B::R| {
+                // Values are visited in ascending order, so only replacing the
+                // current mode on a strictly larger weight breaks ties in favor
+                // of the smaller value.
+                if w.le0() {
+                    return;
+                }
+                match mode {
+                    Some((_, max)) if *max >= w => {}
+                    _ => *mode = Some((v.clone(), w)),
+                }
+            },
+            |mode: Option<(B::Val, B::R)>| mode.map(|(v, _)| v),
+        ))
+        .flat_map_index(|(k, mode)| mode.clone().map(|mode| (k.clone(), mode)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        indexed_zset,
+        operator::group::tester::{group_updates, GroupTransformTester, GroupUpdate},
+        OrdIndexedZSet, RootCircuit, Runtime, Stream,
+    };
+    use proptest::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    fn mode_value_test(workers: usize) {
+        let output: Arc<Mutex<OrdIndexedZSet<usize, usize, isize>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) = circuit.add_input_indexed_zset();
+            input_stream
+                .mode_value()
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        // Group `1` has a clear mode; values `5` and `8` in group `2` are tied.
+        input_handle.append(&mut vec![
+            (1, (1, 1)),
+            (1, (2, 4)),
+            (1, (3, 2)),
+            (2, (8, 3)),
+            (2, (5, 3)),
+            (2, (6, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 1}, 2 => {5 => 1}}
+        );
+
+        // Break the tie in favor of `8`; change the mode of group `1`.
+        input_handle.append(&mut vec![(2, (8, 1)), (1, (2, -3))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {3 => 1}, 2 => {8 => 1}}
+        );
+
+        // Group `1` has no values with positive weights left.
+        input_handle.append(&mut vec![(1, (1, -2)), (1, (2, -1)), (1, (3, -2))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {2 => {8 => 1}}
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn mode_value_test1() {
+        mode_value_test(1);
+    }
+
+    #[test]
+    fn mode_value_test4() {
+        mode_value_test(4);
+    }
+
+    fn mode_value_incremental_test(workers: usize, steps: &[Vec<GroupUpdate>]) {
+        GroupTransformTester::new(
+            workers,
+            |stream: &Stream<RootCircuit, OrdIndexedZSet<usize, usize, isize>>| stream.mode_value(),
+            |group: &[(usize, isize)]| {
+                let mut mode: Option<(usize, isize)> = None;
+                for &(val, w) in group {
+                    if w > 0 && mode.map_or(true, |(_, max)| w > max) {
+                        mode = Some((val, w));
+                    }
+                }
+                mode.map(|(val, _)| (val, 1)).into_iter().collect()
+            },
+        )
+        .run(steps);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn mode_value_incremental_test1(steps in group_updates()) {
+            mode_value_incremental_test(1, &steps);
+        }
+
+        #[test]
+        fn mode_value_incremental_test4(steps in group_updates()) {
+            mode_value_incremental_test(4, &steps);
+        }
+    }
+}
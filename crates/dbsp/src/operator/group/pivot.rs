@@ -0,0 +1,125 @@
+use crate::{
+    algebra::{AddAssignByRef, HasZero, IndexedZSet, MulByRef, UnimplementedSemigroup, ZRingValue},
+    operator::Fold,
+    DBData, OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::collections::BTreeMap;
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Pivots each group into a single wide row.
+    ///
+    /// For each key `k` in the input, the operator outputs one map, with
+    /// weight `1`, from `category(v)` to the aggregated `value(v)` over all
+    /// values `v` in the group.  Values that fall into the same category are
+    /// combined by weighted sum: the entry for category `c` is
+    /// `sum(value(v) * w)` over all `(v, w)` pairs in the group with
+    /// `category(v) == c`.  Every category that occurs in the group has an
+    /// entry in the map, even if its sum is zero.
+    pub fn pivot<FC, FV, C, V>(
+        &self,
+        category: FC,
+        value: FV,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, BTreeMap<C, V>, B::R>>
+    where
+        FC: Fn(&B::Val) -> C + Clone + 'static,
+        FV: Fn(&B::Val) -> V + Clone + 'static,
+        C: DBData,
+        V: DBData + From<B::R> + MulByRef<Output = V> + AddAssignByRef + HasZero,
+    {
+        self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+            BTreeMap::new(),
+            move |row: &mut BTreeMap<C, V>, v: &B::Val, w: B::R| {
+                row.entry(category(v))
+                    .or_insert_with(V::zero)
+                    .add_assign_by_ref(&value(v).mul_by_ref(&V::from(w)));
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, OrdIndexedZSet, Runtime};
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    type PivotOutput = OrdIndexedZSet<usize, BTreeMap<String, isize>, isize>;
+
+    fn row(entries: &[(&str, isize)]) -> BTreeMap<String, isize> {
+        entries
+            .iter()
+            .map(|(category, value)| (category.to_string(), *value))
+            .collect()
+    }
+
+    fn pivot_test(workers: usize) {
+        let output: Arc<Mutex<PivotOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            // Values are `(category, amount)` pairs.
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, (String, isize), isize>();
+            input_stream
+                .pivot(|(category, _)| category.clone(), |&(_, amount)| amount)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, (("east".to_string(), 10), 1)),
+            (1, (("west".to_string(), 5), 1)),
+            // Duplicate categories are summed, taking weights into account.
+            (1, (("west".to_string(), 7), 2)),
+            (2, (("east".to_string(), 3), 1)),
+            (2, (("west".to_string(), 4), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {row(&[("east", 10), ("west", 19)]) => 1},
+                2 => {row(&[("east", 3), ("west", 4)]) => 1}
+            }
+        );
+
+        // Removing the last value of a category removes its column.
+        input_handle.append(&mut vec![
+            (1, (("west".to_string(), 7), -2)),
+            (2, (("east".to_string(), 3), -1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {row(&[("east", 10), ("west", 5)]) => 1},
+                2 => {row(&[("west", 4)]) => 1}
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn pivot_test1() {
+        pivot_test(1);
+    }
+
+    #[test]
+    fn pivot_test4() {
+        pivot_test(4);
+    }
+}
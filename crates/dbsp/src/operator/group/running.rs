@@ -0,0 +1,263 @@
+use crate::{
+    algebra::{IndexedZSet, UnimplementedSemigroup, ZRingValue},
+    operator::{group::flatten_groups, Fold},
+    DBData, OrdIndexedZSet, RootCircuit, Stream,
+};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Computes the running minimum of `column` within each group.
+    ///
+    /// For every value `v` in a group, visited in ascending order, the
+    /// operator outputs `(v, min)`, where `min` is the smallest
+    /// `column(u)` over all values `u <= v` in the group, with the weight of
+    /// `v`.  Values whose weights are zero or negative are skipped.  This
+    /// matches the semantics of SQL `MIN(column) OVER (ORDER BY v)`.
+    ///
+    /// The running minimum never increases along the sorted order of each
+    /// group.
+    pub fn running_min<F, X>(
+        &self,
+        column: F,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, X), B::R>>
+    where
+        F: Fn(&B::Val) -> X + Clone + 'static,
+        X: DBData,
+    {
+        self.running_extremum(column, |current, x| current <= x)
+    }
+
+    /// Computes the running maximum of `column` within each group.
+    ///
+    /// This is the dual of [`running_min`](`Self::running_min`): the second
+    /// component of each output tuple is the largest `column(u)` over all
+    /// values `u <= v` in the group.  The running maximum never decreases
+    /// along the sorted order of each group.
+    pub fn running_max<F, X>(
+        &self,
+        column: F,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, X), B::R>>
+    where
+        F: Fn(&B::Val) -> X + Clone + 'static,
+        X: DBData,
+    {
+        self.running_extremum(column, |current, x| current >= x)
+    }
+
+    /// Walks each group in ascending order, pairing every value with the
+    /// extremum of `column` seen so far.  `keep(current, x)` returns `true`
+    /// if the current extremum still wins over the new value `x`.
+    fn running_extremum<F, X>(
+        &self,
+        column: F,
+        keep: fn(&X, &X) -> bool,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (B::Val, X), B::R>>
+    where
+        F: Fn(&B::Val) -> X + Clone + 'static,
+        X: DBData,
+    {
+        let running = self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+            Vec::new(),
+            move |acc: &mut Vec<((B::Val, X), B::R)>, v: &B::Val, w: B::R| {
+                if w.le0() {
+                    return;
+                }
+                let x = column(v);
+                let extremum = match acc.last() {
+                    Some(((_, current), _)) if keep(current, &x) => current.clone(),
+                    _ => x,
+                };
+                acc.push(((v.clone(), extremum), w));
+            },
+        ));
+
+        flatten_groups(&running)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        indexed_zset,
+        operator::group::tester::{group_updates, GroupUpdate},
+        trace::{BatchReader, Cursor},
+        OrdIndexedZSet, Runtime,
+    };
+    use proptest::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    type RunningOutput = OrdIndexedZSet<usize, ((usize, i64), i64), isize>;
+
+    fn running_extremum_test(workers: usize) {
+        let min_output: Arc<Mutex<RunningOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let max_output: Arc<Mutex<RunningOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let min_output_clone = min_output.clone();
+        let max_output_clone = max_output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            // Values are `(timestamp, measurement)` pairs.
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, (usize, i64), isize>();
+            input_stream
+                .running_min(|&(_, x)| x)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *min_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_stream
+                .running_max(|&(_, x)| x)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *max_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, ((1, 5), 1)),
+            (1, ((2, 7), 1)),
+            (1, ((3, 2), 2)),
+            (1, ((4, 4), 1)),
+            (2, ((1, -1), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*min_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {((1, 5), 5) => 1, ((2, 7), 5) => 1, ((3, 2), 2) => 2, ((4, 4), 2) => 1},
+                2 => {((1, -1), -1) => 1}
+            }
+        );
+        assert_eq!(
+            &*max_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {((1, 5), 5) => 1, ((2, 7), 7) => 1, ((3, 2), 7) => 2, ((4, 4), 7) => 1},
+                2 => {((1, -1), -1) => 1}
+            }
+        );
+
+        // Retracting the first row of group `1` changes the extremum of every
+        // row after it.
+        input_handle.append(&mut vec![(1, ((1, 5), -1)), (1, ((2, 7), -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*min_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {((3, 2), 2) => 2, ((4, 4), 2) => 1},
+                2 => {((1, -1), -1) => 1}
+            }
+        );
+        assert_eq!(
+            &*max_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {((3, 2), 2) => 2, ((4, 4), 4) => 1},
+                2 => {((1, -1), -1) => 1}
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn running_extremum_test1() {
+        running_extremum_test(1);
+    }
+
+    #[test]
+    fn running_extremum_test4() {
+        running_extremum_test(4);
+    }
+
+    type MonotonicOutput = OrdIndexedZSet<usize, (usize, usize), isize>;
+
+    /// Checks that, within each group of `batch`, the running extremum
+    /// changes in a single direction as values increase.
+    fn assert_monotonic(batch: &MonotonicOutput, non_increasing: bool) {
+        let mut cursor = batch.cursor();
+        while cursor.key_valid() {
+            let mut previous: Option<usize> = None;
+            while cursor.val_valid() {
+                let (_, extremum) = *cursor.val();
+                if let Some(previous) = previous {
+                    if non_increasing {
+                        assert!(extremum <= previous, "running min increased in {batch:?}");
+                    } else {
+                        assert!(extremum >= previous, "running max decreased in {batch:?}");
+                    }
+                }
+                previous = Some(extremum);
+                cursor.step_val();
+            }
+            cursor.step_key();
+        }
+    }
+
+    fn running_extremum_monotonic_test(workers: usize, steps: &[Vec<GroupUpdate>]) {
+        let min_output: Arc<Mutex<MonotonicOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let max_output: Arc<Mutex<MonotonicOutput>> = Arc::new(Mutex::new(indexed_zset! {}));
+        let min_output_clone = min_output.clone();
+        let max_output_clone = max_output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, usize, isize>();
+            // Use a column that isn't ordered the same way as the values, so
+            // the extremum doesn't trivially track the first or last value.
+            input_stream
+                .running_min(|v| (v * 5) % 8)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *min_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_stream
+                .running_max(|v| (v * 5) % 8)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *max_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        for updates in steps {
+            let mut tuples: Vec<_> = updates.iter().map(GroupUpdate::as_tuple).collect();
+            input_handle.append(&mut tuples);
+            dbsp.step().unwrap();
+
+            assert_monotonic(&min_output_clone.lock().unwrap(), true);
+            assert_monotonic(&max_output_clone.lock().unwrap(), false);
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn running_extremum_monotonic_test1(steps in group_updates()) {
+            running_extremum_monotonic_test(1, &steps);
+        }
+
+        #[test]
+        fn running_extremum_monotonic_test4(steps in group_updates()) {
+            running_extremum_monotonic_test(4, &steps);
+        }
+    }
+}
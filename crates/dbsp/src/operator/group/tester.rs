@@ -0,0 +1,175 @@
+//! Test harness that checks group operators against a from-scratch
+//! recomputation.
+//!
+//! Incremental bugs in group operators typically only show up after a
+//! specific sequence of insertions and retractions.  [`GroupTransformTester`]
+//! runs an operator in a circuit, feeds it a sequence of updates, and after
+//! every step compares the integrated output of the operator with the output
+//! of a reference function applied to the complete contents of each group.
+
+use crate::{
+    indexed_zset, CollectionHandle, DBSPHandle, OrdIndexedZSet, RootCircuit, Runtime, Stream,
+};
+use proptest::{collection, prelude::*};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+type TestBatch = OrdIndexedZSet<usize, usize, isize>;
+
+/// A single update to the input of a group operator.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum GroupUpdate {
+    /// Add `weight` to the weight of value `val` in group `key`.
+    Insert {
+        key: usize,
+        val: usize,
+        weight: isize,
+    },
+    /// Subtract `weight` from the weight of value `val` in group `key`.
+    Delete {
+        key: usize,
+        val: usize,
+        weight: isize,
+    },
+}
+
+impl GroupUpdate {
+    pub(super) fn as_tuple(&self) -> (usize, (usize, isize)) {
+        match *self {
+            Self::Insert { key, val, weight } => (key, (val, weight)),
+            Self::Delete { key, val, weight } => (key, (val, -weight)),
+        }
+    }
+}
+
+/// Runs a group operator and validates its output after each step.
+pub(super) struct GroupTransformTester<F> {
+    dbsp: DBSPHandle,
+    input_handle: CollectionHandle<usize, (usize, isize)>,
+    output: Arc<Mutex<TestBatch>>,
+    /// Current contents of each group, with zero weights removed.
+    groups: BTreeMap<usize, BTreeMap<usize, isize>>,
+    /// Computes the expected output of a group from its contents, given as
+    /// `(value, weight)` pairs in ascending order of values.
+    reference: F,
+}
+
+impl<F> GroupTransformTester<F>
+where
+    F: Fn(&[(usize, isize)]) -> Vec<(usize, isize)>,
+{
+    /// Builds a circuit with `workers` workers that applies `transformer` to
+    /// its input.
+    pub(super) fn new<T>(workers: usize, transformer: T, reference: F) -> Self
+    where
+        T: Fn(&Stream<RootCircuit, TestBatch>) -> Stream<RootCircuit, TestBatch>
+            + Clone
+            + Send
+            + 'static,
+    {
+        let output = Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (dbsp, input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) = circuit.add_input_indexed_zset();
+            transformer(&input_stream)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output_clone.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        Self {
+            dbsp,
+            input_handle,
+            output,
+            groups: BTreeMap::new(),
+            reference,
+        }
+    }
+
+    /// Feeds `updates` to the circuit as a single input batch, evaluates the
+    /// circuit, and asserts that its accumulated output matches the
+    /// reference.
+    pub(super) fn step(&mut self, updates: &[GroupUpdate]) {
+        let mut tuples = Vec::with_capacity(updates.len());
+
+        for update in updates {
+            let (key, (val, weight)) = update.as_tuple();
+
+            let group = self.groups.entry(key).or_default();
+            let total = group.entry(val).or_insert(0);
+            *total += weight;
+            if *total == 0 {
+                group.remove(&val);
+            }
+            if group.is_empty() {
+                self.groups.remove(&key);
+            }
+
+            tuples.push((key, (val, weight)));
+        }
+
+        self.input_handle.append(&mut tuples);
+        self.dbsp.step().unwrap();
+
+        assert_eq!(
+            &*self.output.lock().unwrap(),
+            &self.expected(),
+            "output mismatch after updates {updates:?}"
+        );
+    }
+
+    /// Applies each batch of updates in turn, validating the output after
+    /// every step.
+    pub(super) fn run(mut self, steps: &[Vec<GroupUpdate>]) {
+        for updates in steps {
+            self.step(updates);
+        }
+        self.dbsp.kill().unwrap();
+    }
+
+    fn expected(&self) -> TestBatch {
+        let mut tuples = Vec::new();
+
+        for (key, group) in self.groups.iter() {
+            let group: Vec<_> = group.iter().map(|(val, w)| (*val, *w)).collect();
+            for (val, w) in (self.reference)(&group) {
+                tuples.push(((*key, val), w));
+            }
+        }
+
+        OrdIndexedZSet::from_tuples((), tuples)
+    }
+}
+
+const NUM_KEYS: usize = 3;
+const NUM_VALS: usize = 8;
+const MAX_UPDATES: usize = 10;
+const MAX_STEPS: usize = 10;
+
+fn group_update() -> impl Strategy<Value = GroupUpdate> {
+    (any::<bool>(), 0..NUM_KEYS, 0..NUM_VALS, 1..=2isize).prop_map(|(insert, key, val, weight)| {
+        if insert {
+            GroupUpdate::Insert { key, val, weight }
+        } else {
+            GroupUpdate::Delete { key, val, weight }
+        }
+    })
+}
+
+/// Generates a sequence of input batches over a small domain of keys and
+/// values, so that the same groups and values are touched repeatedly.
+pub(super) fn group_updates() -> impl Strategy<Value = Vec<Vec<GroupUpdate>>> {
+    collection::vec(
+        collection::vec(group_update(), 0..MAX_UPDATES),
+        1..MAX_STEPS,
+    )
+}
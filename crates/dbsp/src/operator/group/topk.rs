@@ -0,0 +1,754 @@
+use crate::{
+    algebra::{HasZero, IndexedZSet, UnimplementedSemigroup, ZRingValue},
+    operator::{group::flatten_groups, FilterMap, Fold},
+    trace::{Batch, BatchReader, Cursor},
+    Circuit, OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::{cmp::Ordering, collections::VecDeque, iter::once, rc::Rc};
+
+/// Sort direction of a column in
+/// [`topk_by_columns`](`Stream::topk_by_columns`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortDir {
+    Asc,
+    Desc,
+}
+
+/// Extracts a column from values of type `V` for
+/// [`topk_by_columns`](`Stream::topk_by_columns`).
+pub struct ColumnExtractor<V> {
+    cmp: Rc<dyn Fn(&V, &V) -> Ordering>,
+}
+
+impl<V> ColumnExtractor<V> {
+    /// Creates an extractor that orders values by the result of `extract`.
+    pub fn new<C, F>(extract: F) -> Self
+    where
+        C: Ord,
+        F: Fn(&V) -> C + 'static,
+    {
+        Self {
+            cmp: Rc::new(move |x, y| extract(x).cmp(&extract(y))),
+        }
+    }
+}
+
+impl<V> Clone for ColumnExtractor<V> {
+    fn clone(&self) -> Self {
+        Self {
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Keeps the `k` smallest values in each group.
+    ///
+    /// For every key in the input, the operator outputs up to `k` of the
+    /// smallest values associated with the key, along with their weights.
+    /// Values whose weights add up to zero are skipped.
+    ///
+    /// The operator is incremental: when one of the top `k` values of a group
+    /// is retracted, the next smallest value is promoted into the output.
+    pub fn topk_asc(&self, k: usize) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        let topk = self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+            Vec::new(),
+            move |acc: &mut Vec<(B::Val, B::R)>, v: &B::Val, w: B::R| {
+                if acc.len() < k {
+                    acc.push((v.clone(), w));
+                }
+            },
+        ));
+
+        flatten_groups(&topk)
+    }
+
+    /// Keeps the `k` largest values in each group.
+    ///
+    /// This is the dual of [`topk_asc`](`Self::topk_asc`): for every key in
+    /// the input, the operator outputs up to `k` of the largest values
+    /// associated with the key, along with their weights.
+    pub fn topk_desc(&self, k: usize) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        let topk = self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+            VecDeque::new(),
+            move |acc: &mut VecDeque<(B::Val, B::R)>, v: &B::Val, w: B::R| {
+                // Values arrive in ascending order, so the last `k` values
+                // seen are the largest ones.
+                if k == 0 {
+                    return;
+                }
+                if acc.len() == k {
+                    acc.pop_front();
+                }
+                acc.push_back((v.clone(), w));
+            },
+            Vec::from,
+        ));
+
+        flatten_groups(&topk)
+    }
+
+    /// Collects the `k` smallest values in each group into a vector.
+    ///
+    /// This is similar to [`topk_asc`](`Self::topk_asc`), except that
+    /// instead of emitting a row per value, the operator emits a single row
+    /// for each key, with weight `1`, whose value is a vector of up to `k`
+    /// smallest values of the group in ascending order.  Values whose
+    /// weights add up to zero are skipped; weights of the other values are
+    /// discarded.
+    pub fn topk_collect(
+        &self,
+        k: usize,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, Vec<B::Val>, B::R>> {
+        self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+            Vec::new(),
+            move |acc: &mut Vec<B::Val>, v: &B::Val, _w: B::R| {
+                if acc.len() < k {
+                    acc.push(v.clone());
+                }
+            },
+        ))
+    }
+
+    /// Keeps the `global` smallest values across all groups, taking at most
+    /// `per_group` values from each group.
+    ///
+    /// The operator works in two stages.  It first keeps the `per_group`
+    /// smallest values within each group, like
+    /// [`topk_asc`](`Self::topk_asc`).  It then keeps the `global` smallest
+    /// of the surviving values regardless of their group.  Values are
+    /// ranked by value first and by key second, so equal values from
+    /// different groups are ordered deterministically.  Each distinct
+    /// `(key, value)` pair counts once toward both limits regardless of its
+    /// weight, and is output with its original weight.
+    ///
+    /// The second stage ranks all surviving values together and is therefore
+    /// evaluated by a single worker.
+    pub fn topk_capped(
+        &self,
+        per_group: usize,
+        global: usize,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        self.topk_asc(per_group)
+            .map_index(|(k, v)| ((), (v.clone(), k.clone())))
+            .topk_asc(global)
+            .map_index(|(_, (v, k))| (k.clone(), v.clone()))
+    }
+
+    /// Keeps all rows of the `k` smallest keys in the stream.
+    ///
+    /// Unlike [`topk_asc`](`Self::topk_asc`), which keeps up to `k` values
+    /// within every group, this operator ranks the groups themselves: it
+    /// computes the `k` smallest keys that have at least one value, and then
+    /// outputs the complete contents of those groups, i.e., every
+    /// `(key, value)` pair of the input with its original weight.  Each key
+    /// counts once toward the limit regardless of the number of its values
+    /// or their weights.
+    ///
+    /// The keys are ranked by a single worker and the surviving rows are
+    /// recovered by joining the ranked keys back with the input.
+    pub fn topk_rows(&self, k: usize) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        let keys = self
+            .map_index(|(key, _)| ((), key.clone()))
+            .distinct()
+            .topk_asc(k)
+            .map_index(|(_, key)| (key.clone(), ()));
+
+        self.join_index(&keys, |key, val, &()| once((key.clone(), val.clone())))
+    }
+
+    /// Keeps the first `k` values in each group according to a sort order
+    /// over multiple columns, like `ORDER BY a DESC, b ASC LIMIT k`.
+    ///
+    /// Values are compared by each of the `orderings` in turn, with the
+    /// column sorted in the given direction.  Values that are equal in all
+    /// columns are ordered by their natural order.  Values are output with
+    /// their weights, and values whose weights add up to zero are skipped.
+    ///
+    /// The composite order generally differs from the order in which values
+    /// are stored, so the operator keeps the complete contents of each group
+    /// and sorts it whenever the group changes.
+    pub fn topk_by_columns(
+        &self,
+        k: usize,
+        orderings: Vec<(ColumnExtractor<B::Val>, SortDir)>,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        let topk = self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+            Vec::new(),
+            |acc: &mut Vec<(B::Val, B::R)>, v: &B::Val, w: B::R| acc.push((v.clone(), w)),
+            move |mut acc: Vec<(B::Val, B::R)>| {
+                // The sort is stable, so ties keep the ascending order of
+                // values.
+                acc.sort_by(|(x, _), (y, _)| {
+                    orderings
+                        .iter()
+                        .map(|(column, dir)| match dir {
+                            SortDir::Asc => (column.cmp)(x, y),
+                            SortDir::Desc => (column.cmp)(y, x),
+                        })
+                        .find(|ordering| ordering.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                });
+                acc.truncate(k);
+                acc
+            },
+        ));
+
+        flatten_groups(&topk)
+    }
+}
+
+impl<C, B> Stream<C, B>
+where
+    C: Circuit,
+    B: Batch<Time = ()> + Send,
+{
+    /// Keeps the `k` smallest values in each group of each input batch.
+    ///
+    /// This is a non-incremental version of [`topk_asc`](`Self::topk_asc`)
+    /// that computes top-k over each input batch independently.  Since it
+    /// never needs to retract its previous outputs, it only requires weights
+    /// to form a monoid rather than a ring, and works with weight types
+    /// that don't support negation.
+    pub fn stream_topk_asc(&self, k: usize) -> Stream<C, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        self.shard()
+            .apply_named("StreamTopKAsc", move |batch: &B| {
+                topk_batch(batch, k, false)
+            })
+            .mark_sharded()
+    }
+
+    /// Keeps the `k` largest values in each group of each input batch.
+    ///
+    /// This is a non-incremental version of [`topk_desc`](`Self::topk_desc`).
+    /// See [`stream_topk_asc`](`Self::stream_topk_asc`) for details.
+    pub fn stream_topk_desc(&self, k: usize) -> Stream<C, OrdIndexedZSet<B::Key, B::Val, B::R>> {
+        self.shard()
+            .apply_named("StreamTopKDesc", move |batch: &B| {
+                topk_batch(batch, k, true)
+            })
+            .mark_sharded()
+    }
+}
+
+/// Computes top-k values in each group of `batch`, skipping values with zero
+/// weights.
+fn topk_batch<B>(batch: &B, k: usize, descending: bool) -> OrdIndexedZSet<B::Key, B::Val, B::R>
+where
+    B: BatchReader<Time = ()>,
+{
+    let mut tuples = Vec::new();
+    let mut group = Vec::new();
+    let mut cursor = batch.cursor();
+
+    while cursor.key_valid() {
+        while cursor.val_valid() {
+            let weight = cursor.weight();
+            if !weight.is_zero() {
+                group.push((cursor.val().clone(), weight));
+            }
+            cursor.step_val();
+        }
+
+        // Values are visited in ascending order.
+        if descending {
+            group.drain(..group.len().saturating_sub(k));
+        } else {
+            group.truncate(k);
+        }
+        for (val, weight) in group.drain(..) {
+            tuples.push(((cursor.key().clone(), val), weight));
+        }
+
+        cursor.step_key();
+    }
+
+    OrdIndexedZSet::from_tuples((), tuples)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        indexed_zset,
+        operator::group::{
+            tester::{group_updates, GroupTransformTester, GroupUpdate},
+            ColumnExtractor, SortDir,
+        },
+        CollectionHandle, DBSPHandle, OrdIndexedZSet, RootCircuit, Runtime, Stream,
+    };
+    use proptest::prelude::*;
+    use std::{
+        cmp::Reverse,
+        sync::{Arc, Mutex},
+    };
+
+    type Output = Arc<Mutex<OrdIndexedZSet<usize, usize, isize>>>;
+
+    fn topk_test_circuit(
+        workers: usize,
+        k: usize,
+    ) -> (
+        DBSPHandle,
+        CollectionHandle<usize, (usize, isize)>,
+        Output,
+        Output,
+    ) {
+        let asc_output: Output = Arc::new(Mutex::new(indexed_zset! {}));
+        let desc_output: Output = Arc::new(Mutex::new(indexed_zset! {}));
+        let asc_output_clone = asc_output.clone();
+        let desc_output_clone = desc_output.clone();
+
+        let (dbsp, input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) = circuit.add_input_indexed_zset();
+            input_stream
+                .topk_asc(k)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *asc_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_stream
+                .topk_desc(k)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *desc_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        (dbsp, input_handle, asc_output_clone, desc_output_clone)
+    }
+
+    fn topk_retraction_test(workers: usize) {
+        let (mut dbsp, mut input_handle, asc_output, desc_output) = topk_test_circuit(workers, 3);
+
+        input_handle.append(&mut vec![
+            (1, (1, 1)),
+            (1, (2, 2)),
+            (1, (3, 1)),
+            (1, (4, 1)),
+            (1, (5, 1)),
+            (1, (6, 3)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*asc_output.lock().unwrap(),
+            &indexed_zset! {1 => {1 => 1, 2 => 2, 3 => 1}}
+        );
+        assert_eq!(
+            &*desc_output.lock().unwrap(),
+            &indexed_zset! {1 => {4 => 1, 5 => 1, 6 => 3}}
+        );
+
+        // Delete the k-th element of both outputs; the (k+1)-th element must be
+        // promoted.
+        input_handle.append(&mut vec![(1, (3, -1)), (1, (4, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*asc_output.lock().unwrap(),
+            &indexed_zset! {1 => {1 => 1, 2 => 2, 5 => 1}}
+        );
+        assert_eq!(
+            &*desc_output.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 2, 5 => 1, 6 => 3}}
+        );
+
+        // Partially retract a value: it stays in the top-k with its new weight.
+        input_handle.append(&mut vec![(1, (2, -1)), (1, (6, -2))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*asc_output.lock().unwrap(),
+            &indexed_zset! {1 => {1 => 1, 2 => 1, 5 => 1}}
+        );
+        assert_eq!(
+            &*desc_output.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 1, 5 => 1, 6 => 1}}
+        );
+
+        // Shrink the group below `k`.
+        input_handle.append(&mut vec![(1, (1, -1)), (1, (6, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*asc_output.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 1, 5 => 1}}
+        );
+        assert_eq!(
+            &*desc_output.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 1, 5 => 1}}
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn topk_retraction_test1() {
+        topk_retraction_test(1);
+    }
+
+    #[test]
+    fn topk_retraction_test4() {
+        topk_retraction_test(4);
+    }
+
+    fn topk_collect_test(workers: usize) {
+        let output: Arc<Mutex<OrdIndexedZSet<usize, Vec<usize>, isize>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) = circuit.add_input_indexed_zset();
+            input_stream
+                .topk_collect(3)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, (9, 1)),
+            (1, (4, 2)),
+            (1, (7, 1)),
+            (1, (1, 1)),
+            (1, (5, 1)),
+            (2, (3, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {vec![1, 4, 5] => 1}, 2 => {vec![3] => 1}}
+        );
+
+        // Retracting a value promotes the next smallest one into the vector.
+        input_handle.append(&mut vec![(1, (4, -2)), (2, (2, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {vec![1, 5, 7] => 1}, 2 => {vec![2, 3] => 1}}
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    type TestStream = Stream<RootCircuit, OrdIndexedZSet<usize, usize, isize>>;
+
+    fn topk_incremental_test(workers: usize, steps: &[Vec<GroupUpdate>]) {
+        GroupTransformTester::new(
+            workers,
+            |stream: &TestStream| stream.topk_asc(3),
+            |group: &[(usize, isize)]| group.iter().take(3).cloned().collect(),
+        )
+        .run(steps);
+
+        GroupTransformTester::new(
+            workers,
+            |stream: &TestStream| stream.topk_desc(3),
+            |group: &[(usize, isize)]| group.iter().rev().take(3).cloned().collect(),
+        )
+        .run(steps);
+
+        // `ORDER BY v / 4 DESC, v % 4 ASC`.
+        GroupTransformTester::new(
+            workers,
+            |stream: &TestStream| {
+                stream.topk_by_columns(
+                    3,
+                    vec![
+                        (ColumnExtractor::new(|v: &usize| v / 4), SortDir::Desc),
+                        (ColumnExtractor::new(|v: &usize| v % 4), SortDir::Asc),
+                    ],
+                )
+            },
+            |group: &[(usize, isize)]| {
+                let mut group = group.to_vec();
+                group.sort_by_key(|(v, _)| (Reverse(v / 4), v % 4));
+                group.truncate(3);
+                group
+            },
+        )
+        .run(steps);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn topk_incremental_test1(steps in group_updates()) {
+            topk_incremental_test(1, &steps);
+        }
+
+        #[test]
+        fn topk_incremental_test4(steps in group_updates()) {
+            topk_incremental_test(4, &steps);
+        }
+    }
+
+    fn topk_capped_test(workers: usize) {
+        let output: Output = Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) = circuit.add_input_indexed_zset();
+            input_stream
+                .topk_capped(2, 4)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, (1, 1)),
+            (1, (2, 1)),
+            (1, (3, 1)),
+            (2, (2, 2)),
+            (2, (8, 1)),
+            (2, (9, 1)),
+            (3, (4, 1)),
+            (3, (5, 1)),
+            (3, (6, 1)),
+        ]);
+        dbsp.step().unwrap();
+        // At most 2 values per category; 4 values overall, with the tie on
+        // value 2 broken by category.
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {1 => 1, 2 => 1}, 2 => {2 => 2}, 3 => {4 => 1}}
+        );
+
+        // Retracting a value from category 1 promotes its third value,
+        // which displaces the largest value overall.
+        input_handle.append(&mut vec![(1, (1, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 1, 3 => 1}, 2 => {2 => 2}, 3 => {4 => 1}}
+        );
+
+        // Retracting values from category 2 makes room for values from
+        // other categories, but never more than 2 per category.
+        input_handle.append(&mut vec![(2, (2, -2)), (2, (8, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {2 => 1, 3 => 1}, 3 => {4 => 1, 5 => 1}}
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn topk_capped_test1() {
+        topk_capped_test(1);
+    }
+
+    #[test]
+    fn topk_capped_test4() {
+        topk_capped_test(4);
+    }
+
+    fn topk_rows_test(workers: usize) {
+        let output: Arc<Mutex<OrdIndexedZSet<usize, String, isize>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, String, isize>();
+            input_stream
+                .topk_rows(2)
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (3, ("c1".to_string(), 1)),
+            (1, ("a1".to_string(), 1)),
+            (1, ("a2".to_string(), 2)),
+            (1, ("a3".to_string(), 1)),
+            (2, ("b1".to_string(), 1)),
+            (4, ("d1".to_string(), 1)),
+        ]);
+        dbsp.step().unwrap();
+        // All rows of the two smallest keys are kept with their weights.
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {"a1".to_string() => 1, "a2".to_string() => 2, "a3".to_string() => 1},
+                2 => {"b1".to_string() => 1}
+            }
+        );
+
+        // New rows of a surviving key are emitted, and retracting all rows of
+        // a key promotes the next one with all of its rows.
+        input_handle.append(&mut vec![
+            (1, ("a4".to_string(), 1)),
+            (2, ("b1".to_string(), -1)),
+            (3, ("c2".to_string(), 3)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {"a1".to_string() => 1, "a2".to_string() => 2, "a3".to_string() => 1, "a4".to_string() => 1},
+                3 => {"c1".to_string() => 1, "c2".to_string() => 3}
+            }
+        );
+
+        // A smaller key displaces the largest surviving key.
+        input_handle.append(&mut vec![(0, ("z1".to_string(), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {
+                0 => {"z1".to_string() => 1},
+                1 => {"a1".to_string() => 1, "a2".to_string() => 2, "a3".to_string() => 1, "a4".to_string() => 1}
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn topk_rows_test1() {
+        topk_rows_test(1);
+    }
+
+    #[test]
+    fn topk_rows_test4() {
+        topk_rows_test(4);
+    }
+
+    #[test]
+    fn topk_collect_test1() {
+        topk_collect_test(1);
+    }
+
+    #[test]
+    fn topk_collect_test4() {
+        topk_collect_test(4);
+    }
+
+    #[test]
+    fn topk_by_columns_mixed_directions() {
+        let (circuit, (mut input_handle, output)) = RootCircuit::build(|circuit| {
+            let (input_stream, input_handle) =
+                circuit.add_input_indexed_zset::<usize, (i32, i32), isize>();
+            // `ORDER BY a DESC, b ASC LIMIT 3`.
+            let output = input_stream
+                .topk_by_columns(
+                    3,
+                    vec![
+                        (
+                            ColumnExtractor::new(|&(a, _): &(i32, i32)| a),
+                            SortDir::Desc,
+                        ),
+                        (ColumnExtractor::new(|&(_, b): &(i32, i32)| b), SortDir::Asc),
+                    ],
+                )
+                .integrate()
+                .output();
+            (input_handle, output)
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, ((1, 1), 1)),
+            (1, ((2, 3), 1)),
+            (1, ((2, 1), 2)),
+            (1, ((3, 5), 1)),
+            (1, ((1, 0), 1)),
+            (2, ((0, 2), 1)),
+            (2, ((0, 1), 1)),
+        ]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => {(2, 1) => 2, (2, 3) => 1, (3, 5) => 1},
+                2 => {(0, 1) => 1, (0, 2) => 1}
+            }
+        );
+
+        // Retracting the first value promotes the next one.
+        input_handle.append(&mut vec![(1, ((3, 5), -1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => {(1, 0) => 1, (2, 1) => 2, (2, 3) => 1},
+                2 => {(0, 1) => 1, (0, 2) => 1}
+            }
+        );
+    }
+
+    // `u64` weights form a monoid but not a ring, so only the non-incremental
+    // operators can be used with them.
+    fn stream_topk_monoid_weights(workers: usize) {
+        let (mut circuit, (mut input_handle, asc_output, desc_output)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input_stream, input_handle) =
+                    circuit.add_input_indexed_zset::<usize, usize, u64>();
+                let asc_output = input_stream.stream_topk_asc(2).output();
+                let desc_output = input_stream.stream_topk_desc(2).output();
+                (input_handle, asc_output, desc_output)
+            })
+            .unwrap();
+
+        input_handle.append(&mut vec![
+            (1, (5, 1)),
+            (1, (3, 2)),
+            (1, (4, 0)),
+            (1, (1, 7)),
+            (2, (9, 1)),
+        ]);
+        circuit.step().unwrap();
+        assert_eq!(
+            asc_output.consolidate(),
+            indexed_zset! {1 => {1 => 7, 3 => 2}, 2 => {9 => 1}}
+        );
+        assert_eq!(
+            desc_output.consolidate(),
+            indexed_zset! {1 => {3 => 2, 5 => 1}, 2 => {9 => 1}}
+        );
+
+        circuit.kill().unwrap();
+    }
+
+    #[test]
+    fn stream_topk_monoid_weights1() {
+        stream_topk_monoid_weights(1);
+    }
+
+    #[test]
+    fn stream_topk_monoid_weights4() {
+        stream_topk_monoid_weights(4);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    algebra::{
+        AddAssignByRef, HasZero, IndexedZSet, MulByRef, UnimplementedSemigroup, ZRingValue, F64,
+    },
+    operator::{FilterMap, Fold},
+    OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::ops::{Div, Sub};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Computes the weighted population variance of the values in each group.
+    ///
+    /// For each key `k` in the input, the operator outputs a single value
+    /// equal to `sum(w * (v - avg)^2) / sum(w)` over all `(v, w)` pairs in the
+    /// group, where `avg` is the weighted average of the group (see
+    /// [`weighted_avg`](`Self::weighted_avg`)).  The variance is computed in a
+    /// single pass over the group as `(W * S2 - S1 * S1) / (W * W)`, where
+    /// `W = sum(w)`, `S1 = sum(w * v)`, and `S2 = sum(w * v * v)`.  For
+    /// floating point values, this formula can produce a tiny negative
+    /// result due to rounding when the variance is close to zero, so the
+    /// result is clamped at zero.
+    ///
+    /// Groups whose values are all equal, including groups with a single
+    /// value, have variance zero.  Groups whose weights add up to zero have
+    /// no well-defined variance and produce no output.
+    ///
+    /// As with [`weighted_avg`](`Self::weighted_avg`), arithmetic is performed
+    /// in the type of the input values, so the result is truncated towards
+    /// zero for integer values.  Map the input to a floating point type such
+    /// as [`F64`] to get an exact result.
+    pub fn weighted_variance(&self) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>>
+    where
+        B::Val: From<B::R>
+            + MulByRef<Output = B::Val>
+            + AddAssignByRef
+            + Sub<Output = B::Val>
+            + Div<Output = B::Val>
+            + HasZero,
+    {
+        self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+            (B::Val::zero(), B::Val::zero(), B::R::zero()),
+            |(sum, sum_squares, total): &mut (B::Val, B::Val, B::R), v: &B::Val, w: B::R| {
+                let weighted = v.mul_by_ref(&B::Val::from(w.clone()));
+                sum_squares.add_assign_by_ref(&weighted.mul_by_ref(v));
+                sum.add_assign_by_ref(&weighted);
+                total.add_assign_by_ref(&w);
+            },
+            |(sum, sum_squares, total): (B::Val, B::Val, B::R)| {
+                if total.is_zero() {
+                    None
+                } else {
+                    let total = B::Val::from(total);
+                    let variance = (total.mul_by_ref(&sum_squares) - sum.mul_by_ref(&sum))
+                        / total.mul_by_ref(&total);
+                    Some(variance.max(B::Val::zero()))
+                }
+            },
+        ))
+        .flat_map_index(|(k, variance)| variance.clone().map(|variance| (k.clone(), variance)))
+    }
+
+    /// Computes the weighted population standard deviation of the values in
+    /// each group, i.e., the square root of
+    /// [`weighted_variance`](`Self::weighted_variance`).
+    ///
+    /// Groups with zero total weight produce no output, and groups whose
+    /// values are all equal have standard deviation zero.  The variance is
+    /// computed in the type of the input values before being converted to
+    /// [`F64`], so for integer values the truncated variance is used.
+    pub fn weighted_stddev(&self) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, F64, B::R>>
+    where
+        B::Val: From<B::R>
+            + MulByRef<Output = B::Val>
+            + AddAssignByRef
+            + Sub<Output = B::Val>
+            + Div<Output = B::Val>
+            + HasZero
+            + Into<F64>,
+    {
+        self.weighted_variance().map_index(|(k, variance)| {
+            let variance: F64 = variance.clone().into();
+            (k.clone(), F64::new(variance.into_inner().sqrt()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{algebra::F64, indexed_zset, OrdIndexedZSet, Runtime};
+    use std::sync::{Arc, Mutex};
+
+    fn weighted_variance_test(workers: usize) {
+        let variance_output: Arc<Mutex<OrdIndexedZSet<usize, F64, i32>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let stddev_output: Arc<Mutex<OrdIndexedZSet<usize, F64, i32>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let variance_output_clone = variance_output.clone();
+        let stddev_output_clone = stddev_output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) = circuit.add_input_indexed_zset::<usize, F64, i32>();
+            input_stream
+                .weighted_variance()
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *variance_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_stream
+                .weighted_stddev()
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *stddev_output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        let f = F64::new;
+
+        // Group 1: values 2, 4, 4, 4, 5, 5, 7, 9 have mean 5 and variance 4.
+        // Group 2: all values are equal.
+        // Group 3: a single value.
+        // Group 4: zero total weight.
+        // Group 5: all values are equal, but rounding errors make the
+        // single-pass formula negative.
+        input_handle.append(&mut vec![
+            (1, (f(2.0), 1)),
+            (1, (f(4.0), 3)),
+            (1, (f(5.0), 2)),
+            (1, (f(7.0), 1)),
+            (1, (f(9.0), 1)),
+            (2, (f(3.5), 4)),
+            (3, (f(-8.0), 1)),
+            (4, (f(1.0), 2)),
+            (4, (f(6.0), -2)),
+            (5, (f(0.7), 5)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*variance_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {f(4.0) => 1},
+                2 => {f(0.0) => 1},
+                3 => {f(0.0) => 1},
+                5 => {f(0.0) => 1}
+            }
+        );
+        assert_eq!(
+            &*stddev_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {f(2.0) => 1},
+                2 => {f(0.0) => 1},
+                3 => {f(0.0) => 1},
+                5 => {f(0.0) => 1}
+            }
+        );
+
+        // Group 1: adding eight more 5s keeps the mean at 5 and reduces the
+        // variance to 2.
+        // Group 3: values -8 and 8 with equal weights have variance 64.
+        // Group 4: values 1 and 6 with equal weights have variance 6.25.
+        input_handle.append(&mut vec![
+            (1, (f(5.0), 8)),
+            (3, (f(8.0), 1)),
+            (4, (f(6.0), 4)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*variance_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {f(2.0) => 1},
+                2 => {f(0.0) => 1},
+                3 => {f(64.0) => 1},
+                4 => {f(6.25) => 1},
+                5 => {f(0.0) => 1}
+            }
+        );
+        assert_eq!(
+            &*stddev_output_clone.lock().unwrap(),
+            &indexed_zset! {
+                1 => {f(2.0f64.sqrt()) => 1},
+                2 => {f(0.0) => 1},
+                3 => {f(8.0) => 1},
+                4 => {f(2.5) => 1},
+                5 => {f(0.0) => 1}
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn weighted_variance_test1() {
+        weighted_variance_test(1);
+    }
+
+    #[test]
+    fn weighted_variance_test4() {
+        weighted_variance_test(4);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    algebra::{AddAssignByRef, HasZero, IndexedZSet, MulByRef, UnimplementedSemigroup, ZRingValue},
+    operator::{FilterMap, Fold},
+    OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::ops::Div;
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Computes the weighted average of the values in each group.
+    ///
+    /// For each key `k` in the input, the operator outputs a single value
+    /// equal to `sum(v * w) / sum(w)` over all `(v, w)` pairs in the group.
+    /// Groups whose weights add up to zero have no well-defined average and
+    /// produce no output.
+    ///
+    /// The output has the same type as the input values, and the division is
+    /// performed in that type, i.e., the result is truncated towards zero for
+    /// integer values.  Map the input to a floating point type such as
+    /// [`F64`](`crate::algebra::F64`) to get an exact average.
+    ///
+    /// Unlike [`average`](`Self::average`), which is computed as a linear
+    /// aggregate, this operator processes each modified group as a whole and
+    /// is therefore able to handle groups with zero total weight.
+    pub fn weighted_avg(&self) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, B::Val, B::R>>
+    where
+        B::Val: From<B::R>
+            + MulByRef<Output = B::Val>
+            + AddAssignByRef
+            + Div<Output = B::Val>
+            + HasZero,
+    {
+        self.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+            (B::Val::zero(), B::R::zero()),
+            |(sum, total): &mut (B::Val, B::R), v: &B::Val, w: B::R| {
+                sum.add_assign_by_ref(&v.mul_by_ref(&B::Val::from(w.clone())));
+                total.add_assign_by_ref(&w);
+            },
+            |(sum, total): (B::Val, B::R)| {
+                if total.is_zero() {
+                    None
+                } else {
+                    Some(sum / B::Val::from(total))
+                }
+            },
+        ))
+        .flat_map_index(|(k, avg)| avg.clone().map(|avg| (k.clone(), avg)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, OrdIndexedZSet, Runtime};
+    use std::sync::{Arc, Mutex};
+
+    fn weighted_avg_test(workers: usize) {
+        let output: Arc<Mutex<OrdIndexedZSet<usize, isize, isize>>> =
+            Arc::new(Mutex::new(indexed_zset! {}));
+        let output_clone = output.clone();
+
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, move |circuit| {
+            let (input_stream, input_handle) = circuit.add_input_indexed_zset();
+            input_stream
+                .weighted_avg()
+                .integrate()
+                .gather(0)
+                .inspect(move |batch| {
+                    if Runtime::worker_index() == 0 {
+                        *output.lock().unwrap() = batch.clone();
+                    }
+                });
+            input_handle
+        })
+        .unwrap();
+
+        // Group 1: (10 * 1 + 20 * 3) / 4 = 17 (truncated from 17.5).
+        // Group 2: (4 * 2 + 7 * -2) has zero total weight.
+        // Group 3: (-9 * 3) / 3 = -9.
+        input_handle.append(&mut vec![
+            (1, (10, 1)),
+            (1, (20, 3)),
+            (2, (4, 2)),
+            (2, (7, -2)),
+            (3, (-9, 3)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {17 => 1}, 3 => {-9 => 1}}
+        );
+
+        // Group 1: (10 * 1 + 20 * 3 + 30 * 4) / 8 = 23.
+        // Group 2: (4 * 2 + 7 * -2 + 7 * 3) / 3 = 5.
+        // Group 3: the total weight drops to zero.
+        input_handle.append(&mut vec![(1, (30, 4)), (2, (7, 3)), (3, (1, -3))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            &*output_clone.lock().unwrap(),
+            &indexed_zset! {1 => {23 => 1}, 2 => {5 => 1}}
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn weighted_avg_test1() {
+        weighted_avg_test(1);
+    }
+
+    #[test]
+    fn weighted_avg_test4() {
+        weighted_avg_test(4);
+    }
+}
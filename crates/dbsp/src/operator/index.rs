@@ -6,6 +6,7 @@ use crate::{
         Circuit, GlobalNodeId, OwnershipPreference, Scope, Stream,
     },
     circuit_cache_key,
+    operator::Map,
     trace::{
         cursor::Cursor, ord::OrdIndexedZSet, Batch, BatchReader, Builder, Consumer, ValueConsumer,
     },
@@ -79,6 +80,27 @@ where
         self.circuit()
             .add_unary_operator(IndexWith::new(index_func), self)
     }
+
+    /// Re-index an indexed Z-set under a key derived from each of its
+    /// entries.
+    ///
+    /// Applies `key_func` to every `(key, value)` pair in the input batch and
+    /// stores the value under the resulting key, preserving its weight.
+    /// Values that map to the same new key end up in the same group, which
+    /// makes this operator useful for changing the grouping key before
+    /// applying a group operator such as
+    /// [`topk_asc`](`Stream::topk_asc`).
+    pub fn rekey<K2, F>(&self, key_func: F) -> Stream<C, OrdIndexedZSet<K2, CI::Val, CI::R>>
+    where
+        CI: BatchReader<Time = ()>,
+        F: Fn(&CI::Key, &CI::Val) -> K2 + 'static,
+        K2: DBData,
+    {
+        self.circuit().add_unary_operator(
+            Map::new(move |(k, v): (&CI::Key, &CI::Val)| (key_func(k, v), v.clone())),
+            self,
+        )
+    }
 }
 
 /// Operator that generates an indexed representation of a Z-set.
@@ -302,4 +324,60 @@ mod test {
             circuit.step().unwrap();
         }
     }
+
+    #[test]
+    fn rekey_test() {
+        let circuit = RootCircuit::build(move |circuit| {
+            let mut inputs = vec![
+                indexed_zset! { 1 => {10 => 1, 21 => 2}, 2 => {11 => 1}, 3 => {20 => -1} },
+                indexed_zset! { 1 => {10 => -1}, 4 => {12 => 3} },
+            ]
+            .into_iter();
+            // Regroup values by their tens digit; values from different
+            // source keys end up in the same group.
+            let mut outputs = vec![
+                indexed_zset! { 1 => {10 => 1, 11 => 1}, 2 => {20 => -1, 21 => 2} },
+                indexed_zset! { 1 => {11 => 1, 12 => 3}, 2 => {20 => -1, 21 => 2} },
+            ]
+            .into_iter();
+            circuit
+                .add_source(Generator::new(move || inputs.next().unwrap()))
+                .rekey(|_k: &usize, v: &usize| v / 10)
+                .integrate()
+                .inspect(move |fm: &OrdIndexedZSet<usize, usize, isize>| {
+                    assert_eq!(fm, &outputs.next().unwrap())
+                });
+        })
+        .unwrap()
+        .0;
+
+        for _ in 0..2 {
+            circuit.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn rekey_topk() {
+        let circuit = RootCircuit::build(move |circuit| {
+            // (user => region * 100 + score): keep the two lowest scores per
+            // region.
+            let mut inputs = vec![indexed_zset! {
+                1 => {101 => 1}, 2 => {105 => 1}, 3 => {103 => 1}, 4 => {202 => 1}
+            }]
+            .into_iter();
+            let mut outputs =
+                vec![indexed_zset! { 1 => {101 => 1, 103 => 1}, 2 => {202 => 1} }].into_iter();
+            circuit
+                .add_source(Generator::new(move || inputs.next().unwrap()))
+                .rekey(|_user: &usize, v: &usize| v / 100)
+                .topk_asc(2)
+                .inspect(move |fm: &OrdIndexedZSet<usize, usize, isize>| {
+                    assert_eq!(fm, &outputs.next().unwrap())
+                });
+        })
+        .unwrap()
+        .0;
+
+        circuit.step().unwrap();
+    }
 }
@@ -18,6 +18,7 @@ mod differentiate;
 mod distinct;
 mod filter_map;
 mod generator;
+mod group;
 mod index;
 mod input;
 mod integrate;
@@ -42,6 +43,7 @@ pub use delta0::Delta0;
 pub use distinct::Distinct;
 pub use filter_map::{FilterKeys, FilterMap, FilterVals, FlatMap, Map, MapKeys};
 pub use generator::{Generator, GeneratorNested};
+pub use group::{ColumnExtractor, GroupTransformStats, SortDir};
 pub use index::Index;
 use input::Mailbox;
 pub use input::{CollectionHandle, InputHandle, UpsertHandle};
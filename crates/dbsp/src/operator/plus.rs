@@ -214,10 +214,12 @@ mod test {
     use crate::{
         algebra::HasZero,
         circuit::OwnershipPreference,
+        indexed_zset,
         operator::{Generator, Inspect},
         trace::{ord::OrdZSet, Batch},
-        zset, Circuit, RootCircuit,
+        zset, Circuit, OrdIndexedZSet, RootCircuit,
     };
+    use std::vec;
 
     #[test]
     fn scalar_plus() {
@@ -374,4 +376,76 @@ mod test {
             circuit.step().unwrap();
         }
     }
+
+    #[test]
+    fn indexed_zset_plus() {
+        let circuit = RootCircuit::build(move |circuit| {
+            let mut left: vec::IntoIter<OrdIndexedZSet<usize, String, isize>> = vec![
+                // Disjoint keys.
+                indexed_zset! {
+                    1 => {"a".to_string() => 1},
+                    3 => {"c".to_string() => 2}
+                },
+                // Overlapping keys with disjoint and matching values.
+                indexed_zset! {
+                    1 => {"a".to_string() => 1, "b".to_string() => 1},
+                    2 => {"b".to_string() => -1}
+                },
+                // Weights that cancel out exactly.
+                indexed_zset! {
+                    1 => {"a".to_string() => 2},
+                    2 => {"b".to_string() => -3, "c".to_string() => 1}
+                },
+                // Empty input.
+                indexed_zset! {},
+            ]
+            .into_iter();
+            let mut right: vec::IntoIter<OrdIndexedZSet<usize, String, isize>> = vec![
+                indexed_zset! {
+                    2 => {"b".to_string() => 1},
+                    4 => {"d".to_string() => -1}
+                },
+                indexed_zset! {
+                    1 => {"a".to_string() => 2, "c".to_string() => 1},
+                    2 => {"a".to_string() => 1}
+                },
+                indexed_zset! {
+                    1 => {"a".to_string() => -2},
+                    2 => {"b".to_string() => 3, "c".to_string() => -1}
+                },
+                indexed_zset! {
+                    5 => {"e".to_string() => 1}
+                },
+            ]
+            .into_iter();
+            let mut expected: vec::IntoIter<OrdIndexedZSet<usize, String, isize>> = vec![
+                indexed_zset! {
+                    1 => {"a".to_string() => 1},
+                    2 => {"b".to_string() => 1},
+                    3 => {"c".to_string() => 2},
+                    4 => {"d".to_string() => -1}
+                },
+                indexed_zset! {
+                    1 => {"a".to_string() => 3, "b".to_string() => 1, "c".to_string() => 1},
+                    2 => {"a".to_string() => 1, "b".to_string() => -1}
+                },
+                indexed_zset! {},
+                indexed_zset! {
+                    5 => {"e".to_string() => 1}
+                },
+            ]
+            .into_iter();
+
+            let left = circuit.add_source(Generator::new(move || left.next().unwrap()));
+            let right = circuit.add_source(Generator::new(move || right.next().unwrap()));
+            left.plus(&right)
+                .inspect(move |batch| assert_eq!(batch, &expected.next().unwrap()));
+        })
+        .unwrap()
+        .0;
+
+        for _ in 0..4 {
+            circuit.step().unwrap();
+        }
+    }
 }
@@ -2,6 +2,7 @@ mod partitioned;
 mod radix_tree;
 mod range;
 mod rolling_aggregate;
+mod tumbling;
 mod watermark;
 mod window;
 
@@ -0,0 +1,104 @@
+use crate::{algebra::IndexedZSet, DBData, OrdIndexedZSet, RootCircuit, Stream};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet,
+{
+    /// Assign each value in the stream to a tumbling window.
+    ///
+    /// Tumbling windows are fixed-size, non-overlapping time intervals that
+    /// cover the time axis, so every value belongs to exactly one window.
+    /// `bucket` maps each value to the key of its window, e.g., a timestamp
+    /// `ts` to `ts - ts % width` for windows of size `width`.
+    ///
+    /// The output stream is re-indexed by window: it contains `(bucket(v),
+    /// v)` for every value `v` in the input stream, with the weight of `v`.
+    /// The original keys are dropped; if the same value occurs under several
+    /// keys, its weights are added up.  Since the operator is linear, it can
+    /// be applied to both complete collections and changes to them; the
+    /// output is then, respectively, the complete contents or changes to the
+    /// contents of all windows.
+    ///
+    /// Aggregating the output stream computes per-window aggregates.
+    ///
+    /// This is a [`rekey`](`Stream::rekey`) by `bucket`.
+    pub fn tumbling_window<T, F>(
+        &self,
+        bucket: F,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<T, B::Val, B::R>>
+    where
+        T: DBData,
+        F: Fn(&B::Val) -> T + 'static,
+    {
+        self.rekey(move |_key, val| bucket(val))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, operator::Generator, Circuit, OrdIndexedZSet, RootCircuit};
+    use std::vec;
+
+    /// Sensor readings indexed by sensor id.  Values are `(timestamp,
+    /// reading)` pairs.
+    type Readings = OrdIndexedZSet<u64, (u64, i64), isize>;
+
+    #[test]
+    fn tumbling() {
+        let circuit = RootCircuit::build(move |circuit| {
+            let mut input: vec::IntoIter<Readings> = vec![
+                indexed_zset! {
+                    1 => {(10, 5) => 1, (99, 7) => 1, (100, 3) => 1},
+                    2 => {(50, 5) => 1, (150, 2) => 2}
+                },
+                indexed_zset! {
+                    // Retractions are assigned to the same windows as
+                    // insertions.
+                    1 => {(99, 7) => -1, (199, 4) => 1},
+                    // The same value under another key.
+                    3 => {(150, 2) => 1}
+                },
+            ]
+            .into_iter();
+
+            let mut expected_changes: vec::IntoIter<OrdIndexedZSet<u64, (u64, i64), isize>> = vec![
+                indexed_zset! {
+                    0 => {(10, 5) => 1, (50, 5) => 1, (99, 7) => 1},
+                    100 => {(100, 3) => 1, (150, 2) => 2}
+                },
+                indexed_zset! {
+                    0 => {(99, 7) => -1},
+                    100 => {(150, 2) => 1, (199, 4) => 1}
+                },
+            ]
+            .into_iter();
+
+            let mut expected_windows: vec::IntoIter<OrdIndexedZSet<u64, (u64, i64), isize>> = vec![
+                indexed_zset! {
+                    0 => {(10, 5) => 1, (50, 5) => 1, (99, 7) => 1},
+                    100 => {(100, 3) => 1, (150, 2) => 2}
+                },
+                indexed_zset! {
+                    0 => {(10, 5) => 1, (50, 5) => 1},
+                    100 => {(100, 3) => 1, (150, 2) => 3, (199, 4) => 1}
+                },
+            ]
+            .into_iter();
+
+            let windows = circuit
+                .add_source(Generator::new(move || input.next().unwrap()))
+                .tumbling_window(|&(ts, _)| ts - ts % 100);
+
+            windows.inspect(move |batch| assert_eq!(batch, &expected_changes.next().unwrap()));
+            windows
+                .integrate()
+                .inspect(move |batch| assert_eq!(batch, &expected_windows.next().unwrap()));
+        })
+        .unwrap()
+        .0;
+
+        for _ in 0..2 {
+            circuit.step().unwrap();
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::trace::layers::{column_layer::ColumnLayerCursor, Cursor};
+
+/// A cursor that walks through two [`ColumnLayerCursor`]s back to back.
+///
+/// Unlike merging, chaining doesn't consolidate entries: the chained cursor
+/// visits all entries of the first cursor followed by all entries of the
+/// second.  The resulting sequence is sorted only if all keys in the second
+/// cursor are greater than or equal to the keys in the first one, which the
+/// constructor checks in debug builds.
+#[derive(Debug, Clone)]
+pub struct ChainCursor<'s, K, R>
+where
+    K: Ord + Clone,
+    R: Clone,
+{
+    first: ColumnLayerCursor<'s, K, R>,
+    second: ColumnLayerCursor<'s, K, R>,
+}
+
+impl<'s, K, R> ChainCursor<'s, K, R>
+where
+    K: Ord + Clone,
+    R: Clone,
+{
+    /// Chains `first` and `second`.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the first key of `second` is smaller than
+    /// the last key of `first`.
+    pub fn new(first: ColumnLayerCursor<'s, K, R>, second: ColumnLayerCursor<'s, K, R>) -> Self {
+        debug_assert!(
+            match (range_keys(&first).last(), range_keys(&second).first()) {
+                (Some(last), Some(next)) => last <= next,
+                _ => true,
+            },
+            "the keys of the second cursor must not be smaller than the keys of the first cursor",
+        );
+
+        Self { first, second }
+    }
+
+    /// The total number of entries in both cursors.
+    pub fn keys(&self) -> usize {
+        self.first.keys() + self.second.keys()
+    }
+
+    /// Returns `true` if the cursor points at valid data.
+    pub fn valid(&self) -> bool {
+        self.first.valid() || self.second.valid()
+    }
+
+    /// Reveals the current `(key, diff)` pair.
+    pub fn item(&self) -> (&'s K, &'s R) {
+        if self.first.valid() {
+            self.first.item()
+        } else {
+            self.second.item()
+        }
+    }
+
+    /// Advances the cursor by one entry, moving on to the second cursor once
+    /// the first one is exhausted.
+    pub fn step(&mut self) {
+        if self.first.valid() {
+            self.first.step();
+        } else {
+            self.second.step();
+        }
+    }
+
+    /// Rewinds both cursors to their initial state.
+    pub fn rewind(&mut self) {
+        self.first.rewind();
+        self.second.rewind();
+    }
+
+    /// Returns the last key in the chained range, or `None` if both cursors
+    /// are empty.
+    pub fn last_key(&self) -> Option<&'s K> {
+        range_keys(&self.second)
+            .last()
+            .or_else(|| range_keys(&self.first).last())
+    }
+}
+
+/// Returns the keys within the bounds of `cursor`.
+fn range_keys<'s, K, R>(cursor: &ColumnLayerCursor<'s, K, R>) -> &'s [K]
+where
+    K: Ord + Clone,
+    R: Clone,
+{
+    let (lower, upper) = cursor.bounds();
+    &cursor.storage().keys[lower..upper]
+}
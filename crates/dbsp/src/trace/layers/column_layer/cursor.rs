@@ -1,4 +1,5 @@
 use crate::{
+    algebra::{AddAssignByRef, HasZero},
     trace::layers::{advance, column_layer::ColumnLayer, retreat, Cursor},
     utils::cursor_position_oob,
     DBData, DBWeight,
@@ -66,6 +67,146 @@ where
         }
     }
 
+    /// Advances the cursor to the first entry at or after the current
+    /// position whose key satisfies `predicate`, or exhausts it if there is
+    /// no such entry.
+    ///
+    /// `predicate` must be monotone over the sorted keys: once it returns
+    /// `true` for a key, it must return `true` for all larger keys, as is the
+    /// case for, e.g., `|k| k >= threshold`.  This allows the entry to be
+    /// located by binary search.  The precondition is checked in debug
+    /// builds, which visits every remaining key; in release builds the
+    /// cursor ends up at an unspecified position if it doesn't hold.
+    pub fn seek_predicate<P>(&mut self, predicate: P)
+    where
+        P: Fn(&K) -> bool,
+    {
+        unsafe { self.storage.assume_invariants() }
+        if self.valid() {
+            let keys = &self.storage.keys[self.pos as usize..self.bounds.1];
+            let offset = keys.partition_point(|k| !predicate(k));
+            debug_assert!(
+                keys[..offset].iter().all(|k| !predicate(k))
+                    && keys[offset..].iter().all(&predicate),
+                "seek_predicate() called with a predicate that isn't monotone over the keys",
+            );
+            self.pos += offset as isize;
+        }
+    }
+
+    /// Advances the cursor past all entries with the same key as the current
+    /// one.
+    ///
+    /// Unconsolidated leaves may contain several consecutive entries with the
+    /// same key.  This leaves the cursor on the first entry with a different
+    /// key, or exhausts it if there is no such entry.
+    pub fn skip_equal_keys(&mut self) {
+        if self.valid() {
+            let storage = self.storage;
+            let key = &storage.keys[self.pos as usize];
+            self.seek_key_with(|k| k == key);
+        }
+    }
+
+    /// Advances the cursor to the first entry at or after the current
+    /// position with a non-zero diff, or exhausts it if there is no such
+    /// entry.
+    ///
+    /// Unconsolidated leaves may contain entries whose diffs have canceled
+    /// out but that haven't been removed yet.
+    pub fn seek_first_non_zero(&mut self)
+    where
+        R: HasZero,
+    {
+        if self.valid() {
+            let diffs = &self.storage.diffs[self.pos as usize..self.bounds.1];
+            self.pos += diffs
+                .iter()
+                .position(|diff| !diff.is_zero())
+                .unwrap_or(diffs.len()) as isize;
+        }
+    }
+
+    /// Returns the keys whose net weight is non-zero, from the current
+    /// position to the end of the cursor's range, in ascending order.
+    ///
+    /// Unconsolidated leaves may contain several consecutive entries with the
+    /// same key; their diffs are added up and the key is returned at most
+    /// once, or not at all if the diffs cancel out.  Leaves the cursor
+    /// exhausted.
+    pub fn distinct_keys(&mut self) -> Vec<K>
+    where
+        R: HasZero + AddAssignByRef,
+    {
+        let mut keys = Vec::new();
+        while self.valid() {
+            let key = self.current_key().clone();
+            let mut weight = R::zero();
+            while self.valid() && self.current_key() == &key {
+                weight.add_assign_by_ref(self.current_diff());
+                self.step();
+            }
+            if !weight.is_zero() {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// Returns the keys bracketing fractional rank `frac` within the cursor's
+    /// range along with the interpolation weight between them.
+    ///
+    /// Ranks are positions of entries in the range, so `0.0` corresponds to
+    /// the first key and `1.0` to the last one.  For rank `frac * (n - 1)`
+    /// that falls between positions `i` and `i + 1`, returns the keys at those
+    /// positions and the fractional part of the rank, so that an interpolated
+    /// numeric value can be computed as `lo + (hi - lo) * weight`.  Weights
+    /// of entries are not taken into account.
+    ///
+    /// Returns `None` if the range is empty or `frac` is outside of
+    /// `[0.0, 1.0]`.  Does not move the cursor.
+    pub fn value_at_fractional_rank(&self, frac: f64) -> Option<(K, K, f64)> {
+        let (lower, upper) = self.bounds;
+        if lower >= upper || !(0.0..=1.0).contains(&frac) {
+            return None;
+        }
+
+        let rank = frac * (upper - lower - 1) as f64;
+        let lo = rank.floor();
+        let hi = rank.ceil();
+
+        let keys = &self.storage.keys;
+        Some((
+            keys[lower + lo as usize].clone(),
+            keys[lower + hi as usize].clone(),
+            rank - lo,
+        ))
+    }
+
+    /// Returns the sum of the diffs of all entries in the cursor's range
+    /// whose keys fall within `[lo, hi)`.
+    ///
+    /// The sub-range is located by [`seek`](`Cursor::seek`)ing a copy of the
+    /// cursor, so the sum doesn't depend on and does not move the cursor's
+    /// current position.  Returns zero if `hi <= lo`.
+    pub fn sum_diffs_in_range(&self, lo: &K, hi: &K) -> R
+    where
+        R: HasZero + AddAssignByRef,
+    {
+        let mut cursor = self.clone();
+        cursor.rewind();
+        cursor.seek(lo);
+        let start = cursor.pos as usize;
+        cursor.seek(hi);
+        let end = cursor.pos as usize;
+
+        let mut sum = R::zero();
+        for diff in &self.storage.diffs[start..end] {
+            sum.add_assign_by_ref(diff);
+        }
+        sum
+    }
+
     pub fn current_key(&self) -> &K {
         debug_assert!(self.pos >= 0);
         &self.storage.keys[self.pos as usize]
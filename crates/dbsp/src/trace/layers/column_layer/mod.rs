@@ -2,11 +2,13 @@
 //! struct-of-array container
 
 mod builders;
+mod chain;
 mod consumer;
 mod cursor;
 mod tests;
 
 pub use builders::ColumnLayerBuilder;
+pub use chain::ChainCursor;
 pub use consumer::{ColumnLayerConsumer, ColumnLayerValues};
 pub use cursor::ColumnLayerCursor;
 
@@ -16,9 +18,10 @@ use crate::{
     utils::{assume, cast_uninit_vec},
     DBData, DBWeight, NumEntries,
 };
-use size_of::SizeOf;
+use size_of::{Context, SizeOf};
 use std::{
-    cmp::min,
+    cmp::{min, Ordering},
+    error::Error,
     fmt::{self, Display},
     mem::MaybeUninit,
     ops::{Add, AddAssign, Neg},
@@ -79,6 +82,18 @@ impl<K, R> ColumnLayer<K, R> {
         }
     }
 
+    /// Creates a new `ColumnLayer` from sorted keys and their diffs
+    ///
+    /// # Safety
+    ///
+    /// `keys` and `diffs` must have the same length, and `keys` must be
+    /// sorted in strictly ascending order.  Use
+    /// [`try_from_sorted`](`Self::try_from_sorted`) to validate these
+    /// requirements
+    pub unsafe fn from_sorted_unchecked(keys: Vec<K>, diffs: Vec<R>) -> Self {
+        Self::from_parts(keys, diffs, 0)
+    }
+
     /// Get the length of the current leaf
     pub fn len(&self) -> usize {
         unsafe { self.assume_invariants() }
@@ -89,6 +104,22 @@ impl<K, R> ColumnLayer<K, R> {
         self.len() == 0
     }
 
+    /// Returns the approximate number of heap bytes owned by the leaf.
+    ///
+    /// This covers the allocated capacity of the key and diff columns as
+    /// well as any heap memory owned by individual keys and diffs, e.g.,
+    /// the contents of strings.
+    pub fn size_bytes(&self) -> usize
+    where
+        K: SizeOf,
+        R: SizeOf,
+    {
+        let mut context = Context::new();
+        self.keys.size_of_children(&mut context);
+        self.diffs.size_of_children(&mut context);
+        context.size_of().total_bytes()
+    }
+
     unsafe fn set_len(&mut self, length: usize) {
         self.keys.set_len(length);
         self.diffs.set_len(length);
@@ -101,18 +132,36 @@ impl<K, R> ColumnLayer<K, R> {
     }
 
     /// Get a reference to the current leaf's key values
+    ///
+    /// Keys are sorted in ascending order, and `keys()[i]` is the key of the
+    /// `i`th entry of the leaf, whose difference is `diffs()[i]`
     pub fn keys(&self) -> &[K] {
         unsafe { self.assume_invariants() }
         &self.keys
     }
 
+    /// Returns the smallest and largest keys of the leaf, or `None` if the
+    /// leaf is empty
+    ///
+    /// Keys below the leaf's lower bound (see
+    /// [`truncate_below`](`Trie::truncate_below`)) are not taken into account.
+    /// Runs in constant time, since keys are sorted
+    pub fn key_range(&self) -> Option<(&K, &K)> {
+        unsafe { self.assume_invariants() }
+        let keys = &self.keys[self.lower_bound..];
+        Some((keys.first()?, keys.last()?))
+    }
+
     /// Get a mutable reference to the current leaf's key values
     pub fn keys_mut(&mut self) -> &mut [K] {
         unsafe { self.assume_invariants() }
         &mut self.keys
     }
 
-    /// Get a reference to the current leaf's key values
+    /// Get a reference to the current leaf's difference values
+    ///
+    /// The slice is parallel to [`keys`](`Self::keys`): `diffs()[i]` is the
+    /// difference of key `keys()[i]`
     pub fn diffs(&self) -> &[R] {
         unsafe { self.assume_invariants() }
         &self.diffs
@@ -319,6 +368,96 @@ where
     }
 }
 
+impl<K, R> ColumnLayer<K, R>
+where
+    K: Copy + TryInto<i128>,
+{
+    /// Returns `true` if the leaf's keys form a contiguous range of integers,
+    /// i.e., every integer between the smallest and the largest key of the
+    /// leaf is present
+    ///
+    /// In a dense leaf the position of a key can be computed from its
+    /// distance to the smallest key, so seeking is cheap no matter how far
+    /// the target key is, whereas in a large sparse leaf a linear scan may
+    /// be preferable for nearby keys.  Empty leaves are dense.
+    ///
+    /// Assumes that keys are unique, as they are in consolidated leaves.
+    /// Keys below the leaf's lower bound are not taken into account, and
+    /// keys that don't fit into an `i128` are never dense.  Runs in constant
+    /// time
+    pub fn is_dense(&self) -> bool {
+        let (&min, &max) = match self.key_range() {
+            Some(range) => range,
+            None => return true,
+        };
+        let live = self.keys.len() - self.lower_bound;
+
+        match (min.try_into(), max.try_into()) {
+            (Ok(min), Ok(max)) => max
+                .checked_sub(min)
+                .map_or(false, |span: i128| span as u128 + 1 == live as u128),
+            _ => false,
+        }
+    }
+}
+
+impl<K, R> ColumnLayer<K, R>
+where
+    K: Ord,
+{
+    /// Creates a new `ColumnLayer` from keys and their diffs, checking that
+    /// both have the same length and that keys are sorted in strictly
+    /// ascending order
+    pub fn try_from_sorted(keys: Vec<K>, diffs: Vec<R>) -> Result<Self, LeafError> {
+        if keys.len() != diffs.len() {
+            return Err(LeafError::LengthMismatch {
+                keys: keys.len(),
+                diffs: diffs.len(),
+            });
+        }
+
+        for (index, pair) in keys.windows(2).enumerate() {
+            match pair[0].cmp(&pair[1]) {
+                Ordering::Less => {}
+                Ordering::Equal => return Err(LeafError::DuplicateKey { index: index + 1 }),
+                Ordering::Greater => return Err(LeafError::UnsortedKey { index: index + 1 }),
+            }
+        }
+
+        // Safety: we've just checked the invariants
+        Ok(unsafe { Self::from_sorted_unchecked(keys, diffs) })
+    }
+}
+
+/// Error returned by [`ColumnLayer::try_from_sorted`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafError {
+    /// The number of keys doesn't match the number of diffs
+    LengthMismatch { keys: usize, diffs: usize },
+    /// The key at `index` is smaller than the previous key
+    UnsortedKey { index: usize },
+    /// The key at `index` is equal to the previous key
+    DuplicateKey { index: usize },
+}
+
+impl Display for LeafError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LengthMismatch { keys, diffs } => {
+                write!(f, "leaf has {keys} keys but {diffs} diffs")
+            }
+            Self::UnsortedKey { index } => {
+                write!(f, "key at index {index} is smaller than the previous key")
+            }
+            Self::DuplicateKey { index } => {
+                write!(f, "key at index {index} is a duplicate of the previous key")
+            }
+        }
+    }
+}
+
+impl Error for LeafError {}
+
 impl<K, R> ColumnLayer<MaybeUninit<K>, MaybeUninit<R>> {
     /// Drops all keys and diffs within the given range
     ///
@@ -4,8 +4,10 @@ use crate::{
     algebra::{AddAssignByRef, HasZero},
     trace::{
         layers::{
-            column_layer::{ColumnLayerBuilder, ColumnLayerConsumer},
-            Builder, TupleBuilder,
+            column_layer::{
+                ChainCursor, ColumnLayer, ColumnLayerBuilder, ColumnLayerConsumer, LeafError,
+            },
+            export_csv, Builder, Cursor, Trie, TupleBuilder,
         },
         Consumer, ValueConsumer,
     },
@@ -122,6 +124,408 @@ fn standard_consumer(canary: &Canary) -> ColumnLayerConsumer<Item<usize>, Item<i
     ColumnLayerConsumer::from(batcher.done())
 }
 
+#[test]
+fn csv_export() {
+    let mut builder = ColumnLayerBuilder::new();
+    for (key, diff) in [(1, 2), (3, -1), (7, 1)] {
+        builder.push_tuple((key, diff));
+    }
+    let leaf = builder.done();
+
+    let mut csv = Vec::new();
+    export_csv(&mut leaf.cursor(), &mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "key,diff\n1,2\n3,-1\n7,1\n"
+    );
+}
+
+#[test]
+fn diff_extrema() {
+    let mut builder = ColumnLayerBuilder::new();
+    for (key, diff) in [(1, 2), (2, -3), (4, 5), (6, 1)] {
+        builder.push_tuple((key, diff));
+    }
+    let leaf = builder.done();
+
+    assert_eq!(leaf.cursor().min_diff(), Some(-3));
+    assert_eq!(leaf.cursor().max_diff(), Some(5));
+
+    // Only the remaining part of the range is scanned.
+    let mut cursor = leaf.cursor();
+    cursor.step();
+    cursor.step();
+    assert_eq!(cursor.min_diff(), Some(1));
+
+    let empty = leaf.cursor_from(2, 2);
+    assert_eq!(empty.clone().min_diff(), None::<i32>);
+    assert_eq!(empty.clone().max_diff(), None::<i32>);
+}
+
+fn leaf(tuples: &[(usize, i32)]) -> ColumnLayer<usize, i32> {
+    let mut builder = ColumnLayerBuilder::new();
+    for &tuple in tuples {
+        builder.push_tuple(tuple);
+    }
+    builder.done()
+}
+
+#[test]
+fn skip_equal_keys() {
+    let leaf = leaf(&[(1, 1), (2, 1), (2, -1), (2, 3), (4, 2)]);
+
+    let mut cursor = leaf.cursor();
+    cursor.step();
+    assert_eq!(cursor.item(), (&2, &1));
+    cursor.skip_equal_keys();
+    assert_eq!(cursor.item(), (&4, &2));
+
+    // A key without duplicates is skipped like a regular step.
+    cursor.rewind();
+    cursor.skip_equal_keys();
+    assert_eq!(cursor.item(), (&2, &1));
+
+    // Skipping the last run exhausts the cursor, after which the call is a
+    // no-op.
+    cursor.seek(&4);
+    cursor.skip_equal_keys();
+    assert!(!cursor.valid());
+    cursor.skip_equal_keys();
+    assert!(!cursor.valid());
+
+    // The skip doesn't go past the end of the cursor's range.
+    let mut cursor = leaf.cursor_from(1, 3);
+    cursor.skip_equal_keys();
+    assert!(!cursor.valid());
+}
+
+#[test]
+fn distinct_keys() {
+    let leaf = leaf(&[
+        (1, 1),
+        (2, 1),
+        (2, -1),
+        (3, 2),
+        (3, -1),
+        (4, 0),
+        (5, -3),
+        (6, 1),
+        (6, 1),
+        (6, -2),
+        (7, 1),
+    ]);
+
+    let mut cursor = leaf.cursor();
+    assert_eq!(cursor.distinct_keys(), vec![1, 3, 5, 7]);
+    assert!(!cursor.valid());
+    assert_eq!(cursor.distinct_keys(), Vec::<usize>::new());
+
+    // Collection starts at the current position and stops at the end of the
+    // cursor's range.
+    let mut cursor = leaf.cursor();
+    cursor.seek(&3);
+    assert_eq!(cursor.distinct_keys(), vec![3, 5, 7]);
+    assert_eq!(leaf.cursor_from(0, 4).distinct_keys(), vec![1, 3]);
+
+    assert_eq!(
+        ColumnLayer::<usize, i32>::empty().cursor().distinct_keys(),
+        Vec::<usize>::new()
+    );
+}
+
+#[test]
+fn seek_first_non_zero() {
+    let zeros = leaf(&[(1, 0), (2, 0), (3, 2), (4, 0), (5, 0), (6, -1), (7, 0)]);
+
+    // Leading zero-weight entries are skipped.
+    let mut cursor = zeros.cursor();
+    cursor.seek_first_non_zero();
+    assert_eq!(cursor.item(), (&3, &2));
+
+    // The current entry is kept if its diff is non-zero.
+    cursor.seek_first_non_zero();
+    assert_eq!(cursor.item(), (&3, &2));
+
+    // Interior zero-weight entries are skipped.
+    cursor.step();
+    cursor.seek_first_non_zero();
+    assert_eq!(cursor.item(), (&6, &-1));
+
+    // Trailing zero-weight entries exhaust the cursor.
+    cursor.step();
+    cursor.seek_first_non_zero();
+    assert!(!cursor.valid());
+    cursor.seek_first_non_zero();
+    assert!(!cursor.valid());
+
+    // The seek doesn't go past the end of the cursor's range.
+    let mut cursor = zeros.cursor_from(3, 5);
+    cursor.seek_first_non_zero();
+    assert!(!cursor.valid());
+}
+
+#[test]
+fn seek_predicate() {
+    let five = leaf(&[(10, 1), (20, 1), (30, 2), (40, -1), (50, 1)]);
+
+    // Thresholds below, between, on and above the keys.
+    for (threshold, expected) in [
+        (0, Some(10)),
+        (10, Some(10)),
+        (11, Some(20)),
+        (29, Some(30)),
+        (30, Some(30)),
+        (31, Some(40)),
+        (50, Some(50)),
+        (51, None),
+    ] {
+        let mut cursor = five.cursor();
+        cursor.seek_predicate(|&k| k >= threshold);
+        assert_eq!(
+            cursor.valid().then(|| *cursor.current_key()),
+            expected,
+            "k >= {threshold}"
+        );
+    }
+
+    // The search starts at the current position.
+    let mut cursor = five.cursor();
+    cursor.seek(&40);
+    cursor.seek_predicate(|&k| k >= 20);
+    assert_eq!(cursor.current_key(), &40);
+
+    // The search is bounded by the cursor's range.
+    let mut cursor = five.cursor_from(1, 3);
+    cursor.seek_predicate(|&k| k >= 40);
+    assert!(!cursor.valid());
+
+    let mut cursor = leaf(&[]).cursor();
+    cursor.seek_predicate(|&k| k >= 0);
+    assert!(!cursor.valid());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "isn't monotone")]
+fn seek_predicate_non_monotone() {
+    let five = leaf(&[(10, 1), (20, 1), (30, 2), (40, -1), (50, 1)]);
+    five.cursor().seek_predicate(|&k| k == 30);
+}
+
+#[test]
+fn value_at_fractional_rank() {
+    let five = leaf(&[(10, 1), (20, 1), (30, 2), (40, -1), (50, 1)]);
+    let cursor = five.cursor();
+
+    assert_eq!(cursor.value_at_fractional_rank(0.0), Some((10, 10, 0.0)));
+    assert_eq!(cursor.value_at_fractional_rank(0.5), Some((30, 30, 0.0)));
+    assert_eq!(cursor.value_at_fractional_rank(1.0), Some((50, 50, 0.0)));
+
+    let (lo, hi, weight) = cursor.value_at_fractional_rank(0.3).unwrap();
+    assert_eq!((lo, hi), (20, 30));
+    assert!((weight - 0.2).abs() < 1e-9);
+
+    assert_eq!(cursor.value_at_fractional_rank(-0.1), None);
+    assert_eq!(cursor.value_at_fractional_rank(1.5), None);
+    assert_eq!(cursor.value_at_fractional_rank(f64::NAN), None);
+
+    // Ranks are relative to the cursor's range.
+    let cursor = five.cursor_from(1, 4);
+    assert_eq!(cursor.value_at_fractional_rank(0.0), Some((20, 20, 0.0)));
+    assert_eq!(cursor.value_at_fractional_rank(0.75), Some((30, 40, 0.5)));
+    assert_eq!(five.cursor_from(2, 2).value_at_fractional_rank(0.5), None);
+
+    assert_eq!(
+        leaf(&[]).cursor().value_at_fractional_rank(0.5),
+        None::<(usize, usize, f64)>
+    );
+}
+
+#[test]
+fn sum_diffs_in_range() {
+    let tuples = [(10, 1), (20, 3), (30, -2), (40, 5), (50, 7)];
+    let five = leaf(&tuples);
+    let manual_sum = |lo: usize, hi: usize| -> i32 {
+        tuples
+            .iter()
+            .filter(|(k, _)| lo <= *k && *k < hi)
+            .map(|(_, r)| r)
+            .sum()
+    };
+
+    let mut cursor = five.cursor();
+    for lo in (5..=55).step_by(5) {
+        for hi in (5..=55).step_by(5) {
+            assert_eq!(
+                cursor.sum_diffs_in_range(&lo, &hi),
+                manual_sum(lo, hi),
+                "[{lo}, {hi})"
+            );
+        }
+    }
+
+    // `lo` is inclusive and `hi` exclusive.
+    assert_eq!(cursor.sum_diffs_in_range(&20, &40), 1);
+    assert_eq!(cursor.sum_diffs_in_range(&20, &20), 0);
+    assert_eq!(cursor.sum_diffs_in_range(&40, &20), 0);
+
+    // The cursor's position doesn't affect the sum and is preserved.
+    cursor.seek(&40);
+    assert_eq!(cursor.sum_diffs_in_range(&10, &60), 14);
+    assert_eq!(cursor.item(), (&40, &5));
+
+    // Only entries within the cursor's range are counted.
+    assert_eq!(five.cursor_from(1, 4).sum_diffs_in_range(&0, &100), 6);
+
+    assert_eq!(leaf(&[]).cursor().sum_diffs_in_range(&0, &100), 0);
+}
+
+#[test]
+fn chain_cursor() {
+    let first = leaf(&[(1, 1), (3, -1)]);
+    let second = leaf(&[(3, 2), (5, 1), (8, 4)]);
+    let empty = leaf(&[]);
+
+    let mut cursor = ChainCursor::new(first.cursor(), second.cursor());
+    assert_eq!(cursor.keys(), 5);
+    assert_eq!(cursor.last_key(), Some(&8));
+
+    let mut entries = Vec::new();
+    while cursor.valid() {
+        let (key, diff) = cursor.item();
+        entries.push((*key, *diff));
+        cursor.step();
+    }
+    assert_eq!(entries, vec![(1, 1), (3, -1), (3, 2), (5, 1), (8, 4)]);
+
+    cursor.rewind();
+    assert!(cursor.valid());
+    assert_eq!(cursor.item(), (&1, &1));
+
+    let cursor = ChainCursor::new(first.cursor(), empty.cursor());
+    assert_eq!(cursor.last_key(), Some(&3));
+    let cursor = ChainCursor::new(empty.cursor(), second.cursor());
+    assert_eq!(cursor.item(), (&3, &2));
+    let cursor = ChainCursor::new(empty.cursor(), empty.cursor());
+    assert!(!cursor.valid());
+    assert_eq!(cursor.last_key(), None);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic]
+fn chain_cursor_unordered() {
+    let first = leaf(&[(1, 1), (5, 1)]);
+    let second = leaf(&[(3, 1)]);
+    ChainCursor::new(first.cursor(), second.cursor());
+}
+
+#[test]
+fn slice_accessors() {
+    let leaf = leaf(&[(1, 1), (3, -1), (5, 2), (8, 4)]);
+    assert_eq!(leaf.len(), leaf.keys().len());
+    assert_eq!(leaf.len(), leaf.diffs().len());
+
+    let mut cursor = leaf.cursor();
+    let mut entries = Vec::new();
+    while cursor.valid() {
+        let (key, diff) = cursor.item();
+        entries.push((*key, *diff));
+        cursor.step();
+    }
+    let slices: Vec<_> = leaf
+        .keys()
+        .iter()
+        .copied()
+        .zip(leaf.diffs().iter().copied())
+        .collect();
+    assert_eq!(slices, entries);
+}
+
+#[test]
+fn key_range_and_density() {
+    let empty = ColumnLayer::<usize, i32>::empty();
+    assert_eq!(empty.key_range(), None);
+    assert!(empty.is_dense());
+
+    let single = leaf(&[(7, 1)]);
+    assert_eq!(single.key_range(), Some((&7, &7)));
+    assert!(single.is_dense());
+
+    let mut dense = leaf(&[(3, 1), (4, -1), (5, 2), (6, 1)]);
+    assert_eq!(dense.key_range(), Some((&3, &6)));
+    assert!(dense.is_dense());
+
+    let sparse = leaf(&[(1, 1), (3, -1), (5, 2), (1000, 4)]);
+    assert_eq!(sparse.key_range(), Some((&1, &1000)));
+    assert!(!sparse.is_dense());
+
+    // Truncated keys are ignored
+    dense.truncate_below(2);
+    assert_eq!(dense.key_range(), Some((&5, &6)));
+    assert!(dense.is_dense());
+    dense.truncate_below(4);
+    assert_eq!(dense.key_range(), None);
+    assert!(dense.is_dense());
+
+    // Signed keys spanning the whole domain don't overflow
+    let extremes =
+        ColumnLayer::try_from_sorted(vec![i64::MIN, 0, i64::MAX], vec![1, 1, 1]).unwrap();
+    assert_eq!(extremes.key_range(), Some((&i64::MIN, &i64::MAX)));
+    assert!(!extremes.is_dense());
+    let negative = ColumnLayer::try_from_sorted(vec![-2i32, -1, 0, 1], vec![1, 1, 1, 1]).unwrap();
+    assert!(negative.is_dense());
+}
+
+#[test]
+fn try_from_sorted() {
+    let leaf = ColumnLayer::try_from_sorted(vec![1, 2, 5], vec![1, -1, 3]).unwrap();
+    assert_eq!(leaf.as_parts(), (&[1, 2, 5][..], &[1, -1, 3][..], 0));
+    assert!(
+        ColumnLayer::<usize, i32>::try_from_sorted(Vec::new(), Vec::new())
+            .unwrap()
+            .is_empty()
+    );
+
+    assert_eq!(
+        ColumnLayer::try_from_sorted(vec![1, 3, 2], vec![1, 1, 1]),
+        Err(LeafError::UnsortedKey { index: 2 }),
+    );
+    assert_eq!(
+        ColumnLayer::try_from_sorted(vec![1, 1, 2], vec![1, 1, 1]),
+        Err(LeafError::DuplicateKey { index: 1 }),
+    );
+    assert_eq!(
+        ColumnLayer::try_from_sorted(vec![1, 2], vec![1]),
+        Err(LeafError::LengthMismatch { keys: 2, diffs: 1 }),
+    );
+}
+
+#[test]
+fn size_bytes() {
+    assert_eq!(leaf(&[]).size_bytes(), 0);
+
+    // The footprint grows with the number of entries.
+    let mut previous = 0;
+    for len in [1, 2, 10, 100] {
+        let tuples: Vec<_> = (0..len).map(|key| (key, 1)).collect();
+        let size = leaf(&tuples).size_bytes();
+        assert!(size > previous, "{len} entries take {size} bytes");
+        previous = size;
+    }
+
+    // Unused capacity counts towards the footprint.
+    assert!(
+        ColumnLayer::<usize, i32>::with_capacity(10).size_bytes() > leaf(&[(1, 1)]).size_bytes()
+    );
+
+    // Heap memory owned by keys is included.
+    let strings = |len: usize| {
+        ColumnLayer::try_from_sorted(vec!["a".repeat(len), "b".repeat(len)], vec![1i32, 1]).unwrap()
+    };
+    assert!(strings(100).size_bytes() >= strings(1).size_bytes() + 2 * 99);
+}
+
 #[test]
 fn no_double_drops_during_consumption() {
     let canary = Canary::new();
@@ -22,7 +22,8 @@ pub use advance::{advance, advance_erased, advance_raw, retreat};
 use crate::algebra::HasZero;
 use size_of::SizeOf;
 use std::{
-    fmt::Debug,
+    fmt::{Debug, Display},
+    io::{self, Write},
     ops::{Add, Sub},
 };
 
@@ -207,6 +208,10 @@ pub trait Cursor<'s> {
     fn step(&mut self);
 
     /// Move cursor back by one element.
+    ///
+    /// Stepping back from the first element of the cursor's range exhausts
+    /// the cursor.  Together with [`fast_forward`](`Self::fast_forward`),
+    /// this allows generic code to walk any cursor in descending order.
     fn step_reverse(&mut self);
 
     /// Advances the cursor until the location where `key` would be expected.
@@ -216,13 +221,16 @@ pub trait Cursor<'s> {
     fn seek_reverse(&mut self, key: &Self::Key);
 
     /// Returns `true` if the cursor points at valid data. Returns `false` if
-    /// the cursor is exhausted.
+    /// the cursor is exhausted, either by stepping past the end of its range
+    /// or by stepping back past its start.
     fn valid(&self) -> bool;
 
     /// Rewinds the cursor to its initial state.
     fn rewind(&mut self);
 
     /// Moves the cursor to the last position.
+    ///
+    /// Leaves the cursor exhausted if its range is empty.
     fn fast_forward(&mut self);
 
     /// Current position of the cursor.
@@ -230,6 +238,73 @@ pub trait Cursor<'s> {
 
     /// Repositions the cursor to a different range of values.
     fn reposition(&mut self, lower: usize, upper: usize);
+
+    /// Returns the smallest diff between the current position of a leaf
+    /// cursor and the end of its range, or `None` if the cursor is exhausted.
+    ///
+    /// Scans all remaining entries, leaving the cursor exhausted.
+    fn min_diff<K, R>(&mut self) -> Option<R>
+    where
+        Self: Cursor<'s, Item<'s> = (&'s K, &'s R)>,
+        K: 's,
+        R: Ord + Clone + 's,
+    {
+        let mut min: Option<&'s R> = None;
+        while self.valid() {
+            let (_, diff) = self.item();
+            if min.map_or(true, |min| diff < min) {
+                min = Some(diff);
+            }
+            self.step();
+        }
+
+        min.cloned()
+    }
+
+    /// Returns the largest diff between the current position of a leaf
+    /// cursor and the end of its range, or `None` if the cursor is exhausted.
+    ///
+    /// Scans all remaining entries, leaving the cursor exhausted.
+    fn max_diff<K, R>(&mut self) -> Option<R>
+    where
+        Self: Cursor<'s, Item<'s> = (&'s K, &'s R)>,
+        K: 's,
+        R: Ord + Clone + 's,
+    {
+        let mut max: Option<&'s R> = None;
+        while self.valid() {
+            let (_, diff) = self.item();
+            if max.map_or(true, |max| diff > max) {
+                max = Some(diff);
+            }
+            self.step();
+        }
+
+        max.cloned()
+    }
+}
+
+/// Writes the entries of a leaf cursor to `writer` in CSV format, starting
+/// from the current position of the cursor.
+///
+/// The output consists of a `key,diff` header followed by one row per entry
+/// in cursor order.  Keys and diffs are written using their `Display`
+/// implementations without any escaping, so this is only meant for debugging.
+pub fn export_csv<'s, C, K, R, W>(cursor: &mut C, mut writer: W) -> io::Result<()>
+where
+    C: Cursor<'s, Item<'s> = (&'s K, &'s R)>,
+    K: Display + 's,
+    R: Display + 's,
+    W: Write,
+{
+    writeln!(writer, "key,diff")?;
+    while cursor.valid() {
+        let (key, diff) = cursor.item();
+        writeln!(writer, "{key},{diff}")?;
+        cursor.step();
+    }
+
+    Ok(())
 }
 
 /// Trait for types used as offsets into an ordered layer.
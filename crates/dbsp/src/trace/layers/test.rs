@@ -6,7 +6,7 @@ use super::{
 };
 use crate::{algebra::HasZero, trace::consolidation::consolidate, DBData, DBWeight};
 use proptest::{collection::vec, prelude::*};
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, fmt::Debug};
 
 // Unordered vectors of tuples used as test inputs.
 type Tuples1<T, R> = Vec<(T, R)>;
@@ -697,6 +697,32 @@ where
     map.retain(|k, v| retain(k, v));
 }
 
+// Check that walking `cursor` backward with `fast_forward` and `step_reverse`
+// visits the same items as walking it forward, in the opposite order.
+fn assert_reverse_matches_forward<'s, C, T, F>(cursor: &mut C, item: F)
+where
+    C: Cursor<'s> + 's,
+    T: Debug + Eq,
+    F: Fn(C::Item<'s>) -> T,
+{
+    let mut forward = Vec::new();
+    cursor.rewind();
+    while cursor.valid() {
+        forward.push(item(cursor.item()));
+        cursor.step();
+    }
+
+    let mut reverse = Vec::new();
+    cursor.fast_forward();
+    while cursor.valid() {
+        reverse.push(item(cursor.item()));
+        cursor.step_reverse();
+    }
+    reverse.reverse();
+
+    assert_eq!(forward, reverse);
+}
+
 // Check that layer- and map-based representations are equivalent
 // by converting `Tr` to `Map` using `convert` closure.
 fn assert_eq_trie_map1<T, R, Tr, F>(trie: &Tr, map: &Map1<T, R>, convert: F)
@@ -853,6 +879,21 @@ proptest! {
         }
     }
 
+    #[test]
+    fn test_leaf_cursors_reverse(tuples in tuples1(100, 3, 500), lower in 0..500usize, len in 0..500usize) {
+        let trie = tuples_to_trie1::<_, _, ColumnLayer<_, _>>(&tuples);
+        let lower = lower.min(trie.keys());
+        let upper = (lower + len).min(trie.keys());
+        assert_reverse_matches_forward(&mut trie.cursor(), |(k, r)| (*k, *r));
+        assert_reverse_matches_forward(&mut trie.cursor_from(lower, upper), |(k, r)| (*k, *r));
+
+        let trie = tuples_to_trie1::<_, _, OrderedLeaf<_, _>>(&tuples);
+        let lower = lower.min(trie.keys());
+        let upper = upper.min(trie.keys());
+        assert_reverse_matches_forward(&mut trie.cursor(), |&(k, r)| (k, r));
+        assert_reverse_matches_forward(&mut trie.cursor_from(lower, upper), |&(k, r)| (k, r));
+    }
+
     #[test]
     fn test_leaf_layers(left in tuples1(10, 3, 5000), right in tuples1(10, 3, 5000)) {
         test_trie1::<_, _, OrderedLeaf<_, _>, _>(&left, &right, &ordered_leaf_to_map1);
@@ -2,9 +2,12 @@ use crate::db::storage::Storage;
 use crate::{ManagerConfig, ProjectDB, ProjectId, Version};
 use anyhow::{Error as AnyError, Result as AnyResult};
 use fs_extra::{dir, dir::CopyOptions};
+use futures::future::select_all;
 use log::{debug, error, trace};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::VecDeque,
+    io::ErrorKind,
     process::{ExitStatus, Stdio},
     sync::Arc,
 };
@@ -128,6 +131,13 @@ impl Compiler {
         Ok(Self { compiler_task })
     }
 
+    /// Kill the compiler task and wait for it to terminate, so that its
+    /// reference to the database is released.
+    pub(crate) async fn shutdown(mut self) {
+        self.compiler_task.abort();
+        let _ = (&mut self.compiler_task).await;
+    }
+
     async fn compiler_task(config: ManagerConfig, db: Arc<Mutex<ProjectDB>>) -> AnyResult<()> {
         Self::do_compiler_task(config, db).await.map_err(|e| {
             error!("compiler task failed; error: '{e}'");
@@ -139,48 +149,70 @@ impl Compiler {
         /* command_receiver: Receiver<CompilerCommand>, */ config: ManagerConfig,
         db: Arc<Mutex<ProjectDB>>,
     ) -> AnyResult<()> {
-        let mut job: Option<CompilationJob> = None;
+        // Compiling projects requires updating their status.
+        if config.read_only {
+            return Ok(());
+        }
+
+        // Up to `max_concurrent_compilations` SQL jobs and at most one Rust
+        // job in progress.  Rust jobs build the shared workspace, so they
+        // would only contend for its `target` directory and `Cargo.lock` if
+        // run in parallel.  Projects that finished the SQL stage wait in
+        // `rust_queue` for their turn.
+        let mut jobs: Vec<CompilationJob> = Vec::new();
+        let mut rust_queue: VecDeque<(ProjectId, Version)> = VecDeque::new();
 
         loop {
             select! {
                 // Wake up every `COMPILER_POLL_INTERVAL` to check
-                // if we need to abort ongoing compilation.
+                // if we need to abort ongoing compilations.
                 _ = sleep(COMPILER_POLL_INTERVAL) => {
-                    let mut cancel = false;
-                    if let Some(job) = &job {
+                    let mut cancelled = Vec::new();
+                    for (index, job) in jobs.iter().enumerate() {
                         // Project was deleted, updated or the user changed its status
                         // to cancelled -- abort compilation.
                         let descr = db.lock().await.get_project_if_exists(job.project_id).await?;
-                        if let Some(descr) = descr {
-                            if descr.version != job.version || !descr.status.is_compiling() {
-                                cancel = true;
-                            }
+                        let cancel = if let Some(descr) = descr {
+                            descr.version != job.version || !descr.status.is_compiling()
                         } else {
-                            cancel = true;
+                            true
+                        };
+                        if cancel {
+                            cancelled.push(index);
                         }
                     }
-                    if cancel {
-                        job.unwrap().cancel().await;
-                        job = None;
+                    for index in cancelled.into_iter().rev() {
+                        jobs.remove(index).cancel().await;
                     }
+
+                    let mut queued = VecDeque::with_capacity(rust_queue.len());
+                    for (project_id, version) in rust_queue.drain(..) {
+                        let descr = db.lock().await.get_project_if_exists(project_id).await?;
+                        match descr {
+                            Some(descr) if descr.version == version && descr.status.is_compiling() => {
+                                queued.push_back((project_id, version));
+                            }
+                            _ => {}
+                        }
+                    }
+                    rust_queue = queued;
                 }
                 // Compilation job finished - start the next stage of the compilation
                 // (i.e. run the Rust compiler after SQL) or update project status in the
                 // database.
-                Some(exit_status) = async {
-                    if let Some(job) = &mut job {
-                        Some(job.wait().await)
-                    } else {
-                        None
-                    }
-                }, if job.is_some() => {
-                    let project_id = job.as_ref().unwrap().project_id;
-                    let version = job.as_ref().unwrap().version;
+                (exit_status, index) = async {
+                    let (exit_status, index, _) =
+                        select_all(jobs.iter_mut().map(|job| Box::pin(job.wait()))).await;
+                    (exit_status, index)
+                }, if !jobs.is_empty() => {
+                    let job = jobs.remove(index);
+                    let project_id = job.project_id;
+                    let version = job.version;
                     let db = db.lock().await;
 
                     match exit_status {
-                        Ok(status) if status.success() && job.as_ref().unwrap().is_sql() => {
-                            // SQL compiler succeeded -- start the Rust job.
+                        Ok(status) if status.success() && job.is_sql() => {
+                            // SQL compiler succeeded -- queue the Rust job.
                             db.set_project_status_guarded(
                                 project_id,
                                 version,
@@ -198,19 +230,18 @@ impl Compiler {
                             db.set_project_schema(project_id, schema_json).await?;
 
                             debug!("Set ProjectStatus::CompilingRust '{project_id}', version '{version}'");
-                            job = Some(CompilationJob::rust(&config, project_id, version).await?);
+                            rust_queue.push_back((project_id, version));
                         }
-                        Ok(status) if status.success() && job.as_ref().unwrap().is_rust() => {
+                        Ok(status) if status.success() && job.is_rust() => {
                             // Rust compiler succeeded -- declare victory.
                             db.set_project_status_guarded(project_id, version, ProjectStatus::Success).await?;
                             debug!("Set ProjectStatus::Success '{project_id}', version '{version}'");
-                            job = None;
                         }
                         Ok(status) => {
                             // Compilation failed - update project status with the compiler
                             // error message.
-                            let output = job.as_ref().unwrap().error_output(&config).await?;
-                            let status = if job.as_ref().unwrap().is_rust() {
+                            let output = job.error_output(&config).await?;
+                            let status = if job.is_rust() {
                                 ProjectStatus::RustError(format!("{output}\nexit code: {status}"))
                             } else if let Ok(messages) = serde_json::from_str(&output) {
                                     // If we can parse the SqlCompilerMessages
@@ -222,43 +253,46 @@ impl Compiler {
                                     ProjectStatus::SystemError(format!("{output}\nexit code: {status}"))
                             };
                             db.set_project_status_guarded(project_id, version, status).await?;
-                            job = None;
                         }
                         Err(e) => {
-                            let status = if job.unwrap().is_rust() {
+                            let status = if job.is_rust() {
                                 ProjectStatus::SystemError(format!("I/O error with rustc: {e}"))
                             } else {
                                 ProjectStatus::SystemError(format!("I/O error with sql-to-dbsp: {e}"))
                             };
                             db.set_project_status_guarded(project_id, version, status).await?;
-                            job = None;
                         }
                     }
                 }
             }
-            // Pick the next project from the queue.
-            if job.is_none() {
+            // Start the next Rust job once the previous one has finished.
+            if !jobs.iter().any(CompilationJob::is_rust) {
+                if let Some((project_id, version)) = rust_queue.pop_front() {
+                    jobs.push(CompilationJob::rust(&config, project_id, version).await?);
+                }
+            }
+
+            // Pick the next projects from the queue.  Claiming a project moves
+            // it to `CompilingSql`, so it won't be picked again.
+            while jobs.iter().filter(|job| job.is_sql()).count()
+                < config.max_concurrent_compilations
+            {
                 let project = {
                     let db = db.lock().await;
-                    if let Some((project_id, version)) = db.next_job().await? {
+                    if let Some((project_id, version)) = db.claim_next_job().await? {
                         trace!("Next project in the queue: '{project_id}', version '{version}'");
-                        let (_version, code) = db.project_code(project_id).await?;
-                        Some((project_id, version, code))
+                        CompilationJob::write_sql_file(&config, &db, project_id).await?;
+                        Some((project_id, version))
                     } else {
                         None
                     }
                 };
 
-                if let Some((project_id, version, code)) = project {
-                    job = Some(CompilationJob::sql(&config, &code, project_id, version).await?);
-                    db.lock()
-                        .await
-                        .set_project_status_guarded(
-                            project_id,
-                            version,
-                            ProjectStatus::CompilingSql,
-                        )
-                        .await?;
+                match project {
+                    Some((project_id, version)) => {
+                        jobs.push(CompilationJob::sql(&config, project_id, version).await?);
+                    }
+                    None => break,
                 }
             }
         }
@@ -287,15 +321,15 @@ impl CompilationJob {
         self.stage == Stage::Rust
     }
 
-    /// Run SQL-to-DBSP compiler.
-    async fn sql(
+    /// Write the code of the project to the input file of the SQL compiler.
+    ///
+    /// The code is streamed from the database in chunks, so large programs
+    /// are never loaded into memory in full.
+    async fn write_sql_file(
         config: &ManagerConfig,
-        code: &str,
+        db: &ProjectDB,
         project_id: ProjectId,
-        version: Version,
-    ) -> AnyResult<Self> {
-        debug!("Running SQL compiler on project '{project_id}', version '{version}'");
-
+    ) -> AnyResult<()> {
         // Create project directory.
         let sql_file_path = config.sql_file_path(project_id);
         let project_directory = sql_file_path.parent().unwrap();
@@ -307,8 +341,26 @@ impl CompilationJob {
         })?;
 
         // Write SQL code to file.
-        fs::write(&sql_file_path, code).await?;
+        let mut reader = db.project_code_reader(project_id).await?;
+        let mut sql_file = File::create(&sql_file_path).await?;
+        while let Some(chunk) = reader.read_chunk().await? {
+            sql_file.write_all(&chunk).await?;
+        }
+        sql_file.flush().await?;
 
+        Ok(())
+    }
+
+    /// Run SQL-to-DBSP compiler on the SQL file written by
+    /// [`write_sql_file`](`Self::write_sql_file`).
+    async fn sql(
+        config: &ManagerConfig,
+        project_id: ProjectId,
+        version: Version,
+    ) -> AnyResult<Self> {
+        debug!("Running SQL compiler on project '{project_id}', version '{version}'");
+
+        let sql_file_path = config.sql_file_path(project_id);
         let rust_file_path = config.rust_program_path(project_id);
         fs::create_dir_all(rust_file_path.parent().unwrap()).await?;
 
@@ -398,7 +450,8 @@ impl CompilationJob {
             })?;
 
         // Write workspace `Cargo.toml`.  The workspace contains SQL libs and the
-        // generated project crate.
+        // generated project crate.  This is safe because only one Rust job
+        // runs at a time.
         let mut workspace_toml_code = format!(
             "[workspace]\nmembers = [ \"lib/*\", \"{}\"]\n",
             ManagerConfig::crate_name(project_id),
@@ -423,6 +476,8 @@ impl CompilationJob {
                 ))
             })?;
 
+        Self::write_rust_toolchain(config).await?;
+
         let err_file = File::create(&config.compiler_stderr_path(project_id))
             .await
             .map_err(|e| {
@@ -467,6 +522,36 @@ impl CompilationJob {
         })
     }
 
+    /// Pin the Rust toolchain configured in
+    /// [`rust_toolchain`](`ManagerConfig::rust_toolchain`) by writing
+    /// `rust-toolchain.toml` to the workspace.
+    ///
+    /// Removes the file written by a previous run of the manager if no
+    /// toolchain is configured.
+    async fn write_rust_toolchain(config: &ManagerConfig) -> AnyResult<()> {
+        let path = config.rust_toolchain_path();
+
+        if let Some(toolchain) = &config.rust_toolchain {
+            fs::write(&path, format!("[toolchain]\nchannel = \"{toolchain}\"\n"))
+                .await
+                .map_err(|e| {
+                    AnyError::msg(format!("failed to write '{}': '{e}'", path.display()))
+                })?;
+        } else {
+            match fs::remove_file(&path).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    return Err(AnyError::msg(format!(
+                        "failed to remove '{}': '{e}'",
+                        path.display()
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
     /// Async-wait for the compiler to terminate.
     async fn wait(&mut self) -> AnyResult<ExitStatus> {
         let exit_status = self.compiler_process.wait().await?;
@@ -495,3 +580,42 @@ impl CompilationJob {
         let _ = self.compiler_process.kill().await;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::CompilationJob;
+    use crate::ManagerConfig;
+    use clap::Parser;
+    use tokio::fs;
+
+    #[tokio::test]
+    async fn rust_toolchain_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = ManagerConfig::try_parse_from([
+            "pipeline-manager",
+            "--working-directory",
+            temp_dir.path().to_str().unwrap(),
+            "--rust-toolchain",
+            "1.69.0",
+        ])
+        .unwrap();
+        fs::create_dir_all(config.workspace_dir()).await.unwrap();
+
+        CompilationJob::write_rust_toolchain(&config).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(config.rust_toolchain_path())
+                .await
+                .unwrap(),
+            "[toolchain]\nchannel = \"1.69.0\"\n"
+        );
+
+        // Unpinning the toolchain removes the file.
+        let config = ManagerConfig {
+            rust_toolchain: None,
+            ..config
+        };
+        CompilationJob::write_rust_toolchain(&config).await.unwrap();
+        assert!(!config.rust_toolchain_path().exists());
+        CompilationJob::write_rust_toolchain(&config).await.unwrap();
+    }
+}
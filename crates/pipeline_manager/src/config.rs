@@ -1,9 +1,10 @@
 use crate::{PipelineId, ProjectId};
 use anyhow::{Error as AnyError, Result as AnyResult};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use serde::Deserialize;
 use std::{
-    fs::{canonicalize, create_dir_all, File},
+    fs::{canonicalize, create_dir_all, remove_dir_all, File},
+    io::ErrorKind,
     path::{Path, PathBuf},
 };
 
@@ -23,10 +24,54 @@ fn default_sql_compiler_home() -> String {
     "../sql-to-dbsp-compiler".to_string()
 }
 
+const fn default_max_concurrent_compilations() -> usize {
+    1
+}
+
+pub(crate) const fn default_max_name_length() -> usize {
+    255
+}
+
+pub(crate) fn default_pipeline_environment() -> String {
+    "default".to_string()
+}
+
+const fn default_serve_ui() -> bool {
+    true
+}
+
 fn default_db_connection_string() -> String {
     "".to_string()
 }
 
+/// Durability of transactions committed to the manager's database.
+///
+/// Maps to the Postgres `synchronous_commit` setting.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SynchronousCommit {
+    /// Don't wait for the commit record to be flushed to disk.  A crash may
+    /// lose recently committed transactions, but never corrupts the database.
+    Off,
+    /// Wait for the commit record to be flushed to the local disk.
+    Local,
+    /// Wait for the commit record to be flushed to disk, including on
+    /// synchronous standbys if any are configured.
+    #[default]
+    On,
+}
+
+impl SynchronousCommit {
+    /// Value of the Postgres `synchronous_commit` setting.
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Local => "local",
+            Self::On => "on",
+        }
+    }
+}
+
 /// Pipeline manager configuration read from a YAML config file or from command
 /// line arguments.
 #[derive(Parser, Deserialize, Debug, Clone)]
@@ -72,6 +117,36 @@ pub(crate) struct ManagerConfig {
     #[arg(long)]
     pub dbsp_override_path: Option<String>,
 
+    /// Rust toolchain used to compile pipelines, e.g., `1.69.0`, `stable`,
+    /// or `nightly-2023-04-20`.
+    ///
+    /// When set, the manager pins the toolchain by writing a
+    /// `rust-toolchain.toml` file to the generated Rust workspace.  By
+    /// default, pipelines are compiled with the default toolchain of the user
+    /// running the manager.
+    #[serde(default)]
+    #[arg(long)]
+    pub rust_toolchain: Option<String>,
+
+    /// Maximum number of projects compiled by the SQL compiler in parallel.
+    ///
+    /// Only the SQL stage runs concurrently.  All projects are built by
+    /// `cargo` in the same Rust workspace, which shares its `target`
+    /// directory and `Cargo.lock`, so Rust builds run one at a time, in the
+    /// order in which their SQL stage finished.  The default is `1`.
+    #[serde(default = "default_max_concurrent_compilations")]
+    #[arg(long, default_value_t = default_max_concurrent_compilations())]
+    pub max_concurrent_compilations: usize,
+
+    /// Maximum number of pipelines that can run at the same time.
+    ///
+    /// Requests to start a new pipeline fail while this many pipelines
+    /// haven't been shut down, protecting a shared host from running out of
+    /// resources.  The default is no limit.
+    #[serde(default)]
+    #[arg(long)]
+    pub max_running_pipelines: Option<usize>,
+
     /// Compile pipelines in debug mode.
     ///
     /// The default is `false`.
@@ -97,6 +172,95 @@ pub(crate) struct ManagerConfig {
     #[arg(short, long, default_value_t = default_db_connection_string())]
     pub db_connection_string: String,
 
+    /// Durability of transactions committed to the manager's database:
+    /// `off`, `local`, or `on`.
+    ///
+    /// Relaxing this setting improves throughput at the risk of losing the
+    /// most recent changes on a crash.  The default is `on`.
+    #[serde(default)]
+    #[arg(long, value_enum, default_value_t = SynchronousCommit::On)]
+    pub db_synchronous_commit: SynchronousCommit,
+
+    /// Number of connector descriptors to cache in memory, avoiding repeated
+    /// database queries for the same connectors.
+    ///
+    /// The default is `0`, which disables the cache.
+    #[serde(default)]
+    #[arg(long, default_value_t = 0)]
+    pub connector_cache_size: usize,
+
+    /// Treat project names as case-insensitive.
+    ///
+    /// When enabled, project lookups by name ignore case, and creating a
+    /// project whose name differs from an existing one only in case fails.
+    /// Enabling this option fails if the database already contains such
+    /// projects.
+    ///
+    /// The default is `false`.
+    #[serde(default)]
+    #[arg(long)]
+    pub case_insensitive_names: bool,
+
+    /// Maximum length of project and connector names, in characters.
+    ///
+    /// Names are used to derive file and crate names of generated code, so
+    /// creating or renaming a project or connector fails if its name is
+    /// longer than this, as well as if the name is empty or contains control
+    /// characters, `/`, or `\`.  The default is `255`.
+    #[serde(default = "default_max_name_length")]
+    #[arg(long, default_value_t = default_max_name_length())]
+    pub max_name_length: usize,
+
+    /// Compact the database on startup.
+    ///
+    /// Runs `VACUUM FULL` when the manager connects to the database, which
+    /// returns space freed by deleted projects, configs, and pipelines to
+    /// the operating system.  `VACUUM FULL` rewrites every table and holds
+    /// an exclusive lock on each table while doing so, so it can delay
+    /// startup for large databases.
+    ///
+    /// The default is `false`.
+    #[serde(default)]
+    #[arg(long)]
+    pub auto_vacuum_on_startup: bool,
+
+    /// Open the database in read-only mode.
+    ///
+    /// Meant for running the manager against a database it must never
+    /// modify, e.g., a reporting replica.  Requests that modify projects,
+    /// configs, pipelines, or connectors fail, while listing and retrieving
+    /// them keeps working.  Projects are not compiled and startup maintenance
+    /// such as `auto_vacuum_on_startup` is skipped.  The database schema is
+    /// not created or migrated, so the database must have been initialized
+    /// by a manager running in read-write mode.
+    ///
+    /// The default is `false`.
+    #[serde(default)]
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// Deployment environment assigned to pipelines created without an
+    /// explicit environment.
+    ///
+    /// Pipelines are tagged with an environment, e.g., `dev`, `staging`, or
+    /// `prod`, so that pipelines running in different environments can be
+    /// managed by the same manager and listed separately.  Pipelines created
+    /// before environments were introduced also belong to this environment.
+    /// The default is `default`.
+    #[serde(default = "default_pipeline_environment")]
+    #[arg(long, default_value_t = default_pipeline_environment())]
+    pub default_pipeline_environment: String,
+
+    /// Serve the web UI.
+    ///
+    /// When `false`, the manager only serves the REST API and requests to
+    /// the UI endpoints (`/` and `/static`) fail with 404, e.g., for
+    /// deployments where the UI is hosted separately.  The default is
+    /// `true`.
+    #[serde(default = "default_serve_ui")]
+    #[arg(long, default_value_t = default_serve_ui(), action = clap::ArgAction::Set)]
+    pub serve_ui: bool,
+
     /// [Developers only] serve static content from the specified directory.
     /// Allows modifying JavaScript without restarting the server.
     #[arg(short, long)]
@@ -204,6 +368,16 @@ impl ManagerConfig {
                 .into_owned();
         }
 
+        if let Some(toolchain) = &self.rust_toolchain {
+            validate_rust_toolchain(toolchain)?;
+        }
+
+        if self.max_concurrent_compilations == 0 {
+            return Err(AnyError::msg(
+                "'max_concurrent_compilations' must be at least 1",
+            ));
+        }
+
         if let Some(path) = self.static_html.as_mut() {
             *path = canonicalize(&path)
                 .map_err(|e| AnyError::msg(format!("failed to access '{path}': {e}")))?
@@ -214,12 +388,15 @@ impl ManagerConfig {
         Ok(self)
     }
 
+    /// Prefix of the names of generated project crates.
+    pub(crate) const CRATE_NAME_PREFIX: &str = "project";
+
     /// Crate name for a project.
     ///
     /// Note: we rely on the project id and not name, so projects can
     /// be renamed without recompiling.
     pub(crate) fn crate_name(project_id: ProjectId) -> String {
-        format!("project{project_id}")
+        format!("{}{project_id}", Self::CRATE_NAME_PREFIX)
     }
 
     /// Directory where the manager maintains the generated cargo workspace.
@@ -227,6 +404,52 @@ impl ManagerConfig {
         Path::new(&self.working_directory).join("cargo_workspace")
     }
 
+    /// Prefix of the names of scratch crate directories created by
+    /// [`temp_project_dir`](`Self::temp_project_dir`).
+    pub(crate) const TEMP_DIR_PREFIX: &str = "tmp_";
+
+    /// Scratch directory in the workspace for a crate that doesn't belong to
+    /// a project, e.g., to check that SQL code compiles without touching the
+    /// project's own crate.
+    ///
+    /// The directory is named `tmp_{suffix}`, where characters of `suffix`
+    /// other than ASCII letters, digits, `-`, and `_` are replaced with `_`.
+    /// Unlike [`project_dir`](`Self::project_dir`), the name never starts
+    /// with [`CRATE_NAME_PREFIX`](`Self::CRATE_NAME_PREFIX`), so scratch
+    /// crates can't collide with project crates and aren't mistaken for them
+    /// when scanning the workspace.
+    #[allow(dead_code)] // Not used by the manager yet.
+    pub(crate) fn temp_project_dir(&self, suffix: &str) -> PathBuf {
+        let suffix: String = suffix
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.workspace_dir()
+            .join(format!("{}{suffix}", Self::TEMP_DIR_PREFIX))
+    }
+
+    /// Remove the directory returned by
+    /// [`temp_project_dir`](`Self::temp_project_dir`) along with its contents.
+    ///
+    /// Succeeds if the directory doesn't exist.
+    #[allow(dead_code)] // Not used by the manager yet.
+    pub(crate) fn remove_temp_project_dir(&self, suffix: &str) -> AnyResult<()> {
+        let path = self.temp_project_dir(suffix);
+        match remove_dir_all(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(AnyError::msg(format!(
+                "failed to remove '{}': {e}",
+                path.display()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
     /// Where Postgres embed stores the database.
     #[cfg(feature = "pg-embed")]
     pub(crate) fn postgres_embed_data_dir(&self) -> PathBuf {
@@ -304,6 +527,12 @@ impl ManagerConfig {
         self.workspace_dir().join("Cargo.toml")
     }
 
+    /// File that pins the Rust toolchain used to compile the generated
+    /// workspace.
+    pub(crate) fn rust_toolchain_path(&self) -> PathBuf {
+        self.workspace_dir().join("rust-toolchain.toml")
+    }
+
     /// Location of the compiled executable for the project.
     pub(crate) fn project_executable(&self, project_id: ProjectId) -> PathBuf {
         Path::new(&self.workspace_dir())
@@ -329,6 +558,18 @@ impl ManagerConfig {
         self.pipeline_dir(pipeline_id).join("metadata.json")
     }
 
+    /// Directory with Prometheus file-based service discovery configs, one
+    /// per running pipeline (see `scripts/start_prometheus.sh`).
+    pub(crate) fn prometheus_dir(&self) -> PathBuf {
+        Path::new(&self.working_directory).join("prometheus")
+    }
+
+    /// Location to write the Prometheus scrape config for the pipeline.
+    pub(crate) fn prometheus_file_path(&self, pipeline_id: PipelineId) -> PathBuf {
+        self.prometheus_dir()
+            .join(format!("pipeline{pipeline_id}.yaml"))
+    }
+
     /// Location to redirect the pipeline stderr stream (where the pipeline
     /// writes its log records).
     pub(crate) fn log_file_path(&self, pipeline_id: PipelineId) -> PathBuf {
@@ -350,3 +591,127 @@ impl ManagerConfig {
         PathBuf::from(project_directory).join(SCHEMA_FILE_NAME)
     }
 }
+
+/// Check that `toolchain` is a valid value of the `channel` setting in
+/// `rust-toolchain.toml`: a release channel (`stable`, `beta`, `nightly`) or
+/// a version number (`1.69`, `1.69.0`), optionally followed by an archive
+/// date (`nightly-2023-04-20`).
+fn validate_rust_toolchain(toolchain: &str) -> AnyResult<()> {
+    let invalid = || {
+        AnyError::msg(format!(
+            "invalid Rust toolchain '{toolchain}': expected a release channel or a \
+             version number, optionally followed by a date, e.g., 'nightly-2023-04-20'"
+        ))
+    };
+
+    let (channel, date) = match toolchain.split_once('-') {
+        Some((channel, date)) => (channel, Some(date)),
+        None => (toolchain, None),
+    };
+
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let is_version = || {
+        let parts: Vec<&str> = channel.split('.').collect();
+        (2..=3).contains(&parts.len()) && parts.iter().all(|p| is_number(p))
+    };
+    if !matches!(channel, "stable" | "beta" | "nightly") && !is_version() {
+        return Err(invalid());
+    }
+
+    if let Some(date) = date {
+        let parts: Vec<&str> = date.split('-').collect();
+        let lengths: Vec<usize> = parts.iter().map(|p| p.len()).collect();
+        if lengths != [4, 2, 2] || !parts.iter().all(|p| is_number(p)) {
+            return Err(invalid());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate_rust_toolchain, ManagerConfig};
+    use crate::ProjectId;
+    use clap::Parser;
+
+    #[test]
+    fn rust_toolchain_format() {
+        for toolchain in [
+            "stable",
+            "beta",
+            "nightly",
+            "1.69",
+            "1.69.0",
+            "nightly-2023-04-20",
+            "1.69.0-2023-04-20",
+        ] {
+            assert!(validate_rust_toolchain(toolchain).is_ok(), "{toolchain}");
+        }
+
+        for toolchain in [
+            "",
+            "1",
+            "1.69.0.1",
+            "1.x",
+            "latest",
+            "nightly-2023-4-20",
+            "nightly-x86_64-unknown-linux-gnu",
+            "stable\"\nchannel = \"nightly",
+        ] {
+            assert!(validate_rust_toolchain(toolchain).is_err(), "{toolchain}");
+        }
+    }
+
+    #[test]
+    fn temp_project_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = ManagerConfig::try_parse_from([
+            "pipeline-manager",
+            "--working-directory",
+            temp_dir.path().to_str().unwrap(),
+        ])
+        .unwrap();
+
+        // Names used by the orphan cleanup scanner: `project{N}`.
+        let is_project_crate = |name: &str| {
+            name.strip_prefix(ManagerConfig::CRATE_NAME_PREFIX)
+                .map_or(false, |id| id.parse::<i64>().is_ok())
+        };
+        assert!(is_project_crate(
+            config
+                .project_dir(ProjectId(5))
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+        ));
+
+        for suffix in ["check", "", "5", "project5", "../project5", "a/b", "x y"] {
+            let dir = config.temp_project_dir(suffix);
+            assert_eq!(Some(config.workspace_dir().as_path()), dir.parent());
+            let name = dir.file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with(ManagerConfig::TEMP_DIR_PREFIX), "{name}");
+            assert!(
+                !name.starts_with(ManagerConfig::CRATE_NAME_PREFIX),
+                "{name}"
+            );
+            assert!(!is_project_crate(name), "{name}");
+        }
+        assert_eq!(
+            config.workspace_dir().join("tmp_check-1_x"),
+            config.temp_project_dir("check-1_x")
+        );
+        assert_eq!(
+            config.workspace_dir().join("tmp____a"),
+            config.temp_project_dir("../a")
+        );
+
+        let dir = config.temp_project_dir("check");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        config.remove_temp_project_dir("check").unwrap();
+        assert!(!dir.exists());
+        // Removing a missing directory is a no-op.
+        config.remove_temp_project_dir("check").unwrap();
+    }
+}
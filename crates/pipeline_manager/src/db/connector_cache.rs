@@ -0,0 +1,75 @@
+use super::{ConnectorDescr, ConnectorId};
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// A small LRU cache of connector descriptors.
+///
+/// Used by [`ProjectDB`](`super::ProjectDB`) to avoid repeatedly querying
+/// the database for the same connectors.  The owner of the cache is
+/// responsible for invalidating entries when connectors are modified.
+pub(crate) struct ConnectorCache {
+    capacity: usize,
+    inner: Mutex<ConnectorCacheInner>,
+}
+
+#[derive(Default)]
+struct ConnectorCacheInner {
+    /// Cached descriptors along with the time of their last use.
+    entries: BTreeMap<ConnectorId, (ConnectorDescr, u64)>,
+    /// Logical clock incremented on every access.
+    clock: u64,
+}
+
+impl ConnectorCache {
+    /// Create a cache that holds up to `capacity` connectors.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(ConnectorCacheInner::default()),
+        }
+    }
+
+    /// Look up `connector_id`, marking it as recently used.
+    pub(crate) fn get(&self, connector_id: ConnectorId) -> Option<ConnectorDescr> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+
+        inner
+            .entries
+            .get_mut(&connector_id)
+            .map(|(descr, last_used)| {
+                *last_used = clock;
+                descr.clone()
+            })
+    }
+
+    /// Add `descr` to the cache, evicting the least recently used entry if
+    /// the cache is full.
+    pub(crate) fn insert(&self, descr: ConnectorDescr) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+
+        if inner.entries.len() >= self.capacity && !inner.entries.contains_key(&descr.connector_id)
+        {
+            let lru = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(connector_id, _)| *connector_id);
+            if let Some(lru) = lru {
+                inner.entries.remove(&lru);
+            }
+        }
+        inner.entries.insert(descr.connector_id, (descr, clock));
+    }
+
+    /// Remove `connector_id` from the cache.
+    pub(crate) fn invalidate(&self, connector_id: ConnectorId) {
+        self.inner.lock().unwrap().entries.remove(&connector_id);
+    }
+}
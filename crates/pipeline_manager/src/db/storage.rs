@@ -1,10 +1,13 @@
 use super::{
-    AttachedConnector, ConfigDescr, ConfigId, ConnectorDescr, ConnectorId, ConnectorType, DBError,
-    PipelineDescr, PipelineId, ProjectDescr, ProjectId, Version,
+    redact_secrets, ArchiveId, AttachedConnector, ConfigDescr, ConfigId, ConnectorDescr,
+    ConnectorId, ConnectorSpec, ConnectorType, DBError, PipelineDescr, PipelineId, PipelineStatus,
+    ProjectArchive, ProjectBundle, ProjectDescr, ProjectId, ProjectTree, Relation,
+    SchemaDriftWarning, Version, VersionEntry,
 };
 use crate::{Direction, ProjectStatus};
 use anyhow::{anyhow, Result as AnyResult};
 use async_trait::async_trait;
+use std::collections::HashSet;
 
 /// The storage trait contains the methods to interact with the pipeline manager
 /// storage layer (e.g., PostgresDB) to implement the public API.
@@ -14,8 +17,22 @@ use async_trait::async_trait;
 pub(crate) trait Storage {
     async fn reset_project_status(&self) -> AnyResult<()>;
 
+    /// Queue all successfully compiled projects for recompilation, e.g.,
+    /// after a compiler upgrade.
+    ///
+    /// Moves every project in the [`ProjectStatus::Success`] state to
+    /// [`ProjectStatus::Pending`].  Projects that are pending, being
+    /// compiled, or failed to compile are left unchanged.  Returns the number
+    /// of requeued projects.
+    async fn requeue_all_successful_projects(&self) -> AnyResult<usize>;
+
+    /// Retrieve all projects, ordered by id.
     async fn list_projects(&self) -> AnyResult<Vec<ProjectDescr>>;
 
+    /// List projects that have never been submitted for compilation, i.e.,
+    /// projects in the [`ProjectStatus::None`] state.
+    async fn uncompiled_projects(&self) -> AnyResult<Vec<ProjectDescr>>;
+
     /// Retrieve project descriptor.
     ///
     /// Returns a `DBError:UnknownProject` error if `project_id` is not found in
@@ -93,16 +110,140 @@ pub(crate) trait Storage {
         Ok(())
     }
 
+    /// Retrieve project descriptor along with all configs of the project.
+    ///
+    /// Returns a `DBError:UnknownProject` error if `project_id` is not found in
+    /// the database.
+    async fn get_project_tree(&self, project_id: ProjectId) -> AnyResult<ProjectTree> {
+        let project = self.get_project(project_id).await?;
+        let configs = self
+            .list_configs()
+            .await?
+            .into_iter()
+            .filter(|config| config.project_id == Some(project_id))
+            .collect();
+
+        Ok(ProjectTree { project, configs })
+    }
+
+    /// Retrieve ids of projects with an input or output relation named
+    /// `relation_name`.
+    ///
+    /// Relation names are compared case-insensitively, since the SQL compiler
+    /// converts unquoted identifiers to upper case.  Projects without a
+    /// schema are skipped, and so are projects whose schema can't be parsed,
+    /// with a warning.
+    async fn projects_referencing_relation(
+        &self,
+        relation_name: &str,
+    ) -> AnyResult<Vec<ProjectId>> {
+        let mut result = Vec::new();
+        for project in self.list_projects().await? {
+            let schema = match project.parsed_schema() {
+                Ok(Some(schema)) => schema,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::warn!("{e}");
+                    continue;
+                }
+            };
+            if schema
+                .inputs
+                .iter()
+                .chain(schema.outputs.iter())
+                .any(|relation| relation.name.eq_ignore_ascii_case(relation_name))
+            {
+                result.push(project.project_id);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Check the connectors attached to a config against the current schema of
+    /// the config's project.
+    ///
+    /// Each attached connector refers to a relation of the project: an input
+    /// connector to an input relation (table) and an output connector to an
+    /// output relation (view).  Returns a warning for every connector whose
+    /// relation no longer exists, e.g., because it was dropped from the
+    /// project.  Relation names are compared case-insensitively.
+    ///
+    /// Returns no warnings if the config has no project or the project doesn't
+    /// have a valid schema yet.
+    async fn detect_config_schema_drift(
+        &self,
+        config_id: ConfigId,
+    ) -> AnyResult<Vec<SchemaDriftWarning>> {
+        let config = self.get_config(config_id).await?;
+        let schema = match config.project_id {
+            Some(project_id) => match self.get_project(project_id).await?.parsed_schema() {
+                Ok(Some(schema)) => schema,
+                Ok(None) => return Ok(Vec::new()),
+                Err(e) => {
+                    log::warn!("{e}");
+                    return Ok(Vec::new());
+                }
+            },
+            None => return Ok(Vec::new()),
+        };
+        let contains = |relations: &[Relation], name: &str| {
+            relations
+                .iter()
+                .any(|relation| relation.name.eq_ignore_ascii_case(name))
+        };
+
+        Ok(config
+            .attached_connectors
+            .into_iter()
+            .filter(|ac| match ac.direction {
+                Direction::Input => !contains(&schema.inputs, &ac.config),
+                Direction::Output => !contains(&schema.outputs, &ac.config),
+                Direction::InputOutput => {
+                    !contains(&schema.inputs, &ac.config) && !contains(&schema.outputs, &ac.config)
+                }
+            })
+            .map(|ac| SchemaDriftWarning {
+                attached_connector: ac.uuid,
+                connector_id: ac.connector_id,
+                direction: ac.direction,
+                relation: ac.config,
+            })
+            .collect())
+    }
+
     /// Retrieve code of the specified project along with the project's
     /// meta-data.
     async fn project_code(&self, project_id: ProjectId) -> AnyResult<(ProjectDescr, String)>;
 
+    /// Retrieve the SHA-256 hash of the code of the specified project as a
+    /// lowercase hex string.
+    ///
+    /// The hash only depends on the code, so it can be used to detect whether
+    /// the code has actually changed, e.g., to skip redeploying a pipeline,
+    /// regardless of changes to the project's name or description.
+    async fn project_code_hash(&self, project_id: ProjectId) -> AnyResult<String>;
+
     /// Create a new project.
+    ///
+    /// If `idempotency_key` is specified and a project was already created
+    /// with the same key, returns the id and current version of that project
+    /// instead of creating a new one, so that clients can safely retry
+    /// requests whose outcome they don't know.  Keys are kept until the
+    /// project is deleted.
+    ///
+    /// Returns a `DBError::InvalidName` error if `project_name` is empty, too
+    /// long, or contains control characters or path separators.  The same
+    /// applies to names passed to [`update_project`](`Self::update_project`),
+    /// [`rename_project`](`Self::rename_project`),
+    /// [`new_connector`](`Self::new_connector`), and
+    /// [`update_connector`](`Self::update_connector`).
     async fn new_project(
         &self,
         project_name: &str,
         project_description: &str,
         project_code: &str,
+        idempotency_key: Option<&str>,
     ) -> AnyResult<(ProjectId, Version)>;
 
     /// Update project name, description and, optionally, code.
@@ -115,12 +256,46 @@ pub(crate) trait Storage {
         project_code: &Option<String>,
     ) -> AnyResult<Version>;
 
+    /// Change the name of a project.
+    ///
+    /// Unlike [`update_project`](`Self::update_project`), this never modifies
+    /// the version, code, or compilation status of the project.  Returns a
+    /// `DBError::DuplicateProjectName` error if another project with
+    /// `new_name` already exists.
+    async fn rename_project(&self, project_id: ProjectId, new_name: &str) -> AnyResult<()>;
+
+    /// Change the description of a project.
+    ///
+    /// Like [`rename_project`](`Self::rename_project`), this never modifies
+    /// the name, version, code, or compilation status of the project.
+    async fn update_project_description(
+        &self,
+        project_id: ProjectId,
+        description: &str,
+    ) -> AnyResult<()>;
+
+    /// Increment project version without changing its code.
+    ///
+    /// Resets project status and schema like an update of the project code
+    /// does, so that the project can be recompiled, e.g., after a compiler
+    /// upgrade.  Returns the new version.
+    async fn bump_project_version(&self, project_id: ProjectId) -> AnyResult<Version>;
+
     /// Retrieve project descriptor.
     ///
     /// Returns `None` if `project_id` is not found in the database.
     async fn get_project_if_exists(&self, project_id: ProjectId)
         -> AnyResult<Option<ProjectDescr>>;
 
+    /// Retrieve the highest version of the project that compiled
+    /// successfully.
+    ///
+    /// This may be older than the current version of the project if the
+    /// project was modified after the last successful build.  Returns `None`
+    /// if no version of the project has compiled successfully and
+    /// `DBError::UnknownProject` if the project doesn't exist.
+    async fn latest_successful_version(&self, project_id: ProjectId) -> AnyResult<Option<Version>>;
+
     /// Lookup project by name.
     async fn lookup_project(&self, project_name: &str) -> AnyResult<Option<ProjectDescr>>;
 
@@ -159,19 +334,122 @@ pub(crate) trait Storage {
     /// same transaction that sets status to  [`ProjectStatus::CompilingRust`].
     async fn set_project_schema(&self, project_id: ProjectId, schema: String) -> AnyResult<()>;
 
+    /// Reset project schema to null, leaving its status untouched.
+    ///
+    /// [`set_project_status`](`Self::set_project_status`) also resets the
+    /// schema, since a schema only makes sense for a particular compilation
+    /// status.  Use this method instead to invalidate a stale schema without
+    /// affecting the status of the project.
+    ///
+    /// # Note
+    /// Doesn't check that the project exists.
+    async fn clear_project_schema(&self, project_id: ProjectId) -> AnyResult<()>;
+
     /// Delete project from the database.
     ///
     /// This will delete all project configs and pipelines.
     async fn delete_project(&self, project_id: ProjectId) -> AnyResult<()>;
 
+    /// Collect the complete state of a project: its descriptor, code, and
+    /// configs (see [`get_project_tree`](`Self::get_project_tree`)).
+    ///
+    /// Returns a `DBError:UnknownProject` error if `project_id` is not found in
+    /// the database.
+    async fn project_bundle(&self, project_id: ProjectId) -> AnyResult<ProjectBundle> {
+        let (_, code) = self.project_code(project_id).await?;
+        let ProjectTree { project, configs } = self.get_project_tree(project_id).await?;
+
+        Ok(ProjectBundle {
+            project,
+            code,
+            configs,
+        })
+    }
+
+    /// Store a snapshot of the current state of a project (see
+    /// [`project_bundle`](`Self::project_bundle`)) as a new archive.
+    ///
+    /// Archives can't be modified once created and are retained when the
+    /// project is deleted.
+    async fn archive_project(&self, project_id: ProjectId, reason: &str) -> AnyResult<ArchiveId>;
+
+    /// Retrieve all archives of a project, ordered by id.
+    ///
+    /// Includes archives of deleted projects.
+    async fn list_archives(&self, project_id: ProjectId) -> AnyResult<Vec<ProjectArchive>>;
+
+    /// Retrieve a project archive.
+    ///
+    /// Returns a `DBError:UnknownArchive` error if `archive_id` is not found in
+    /// the database.
+    async fn get_archive(&self, archive_id: ArchiveId) -> AnyResult<ProjectArchive>;
+
+    /// Retrieve all versions of a project in ascending order, starting with
+    /// the version the project was created with.
+    ///
+    /// A new version is recorded whenever the code of the project changes or
+    /// its version is bumped.  Projects created before version history was
+    /// introduced only have the version that was current when the manager
+    /// was upgraded.
+    ///
+    /// Returns a `DBError:UnknownProject` error if `project_id` is not found in
+    /// the database.
+    async fn project_version_history(&self, project_id: ProjectId) -> AnyResult<Vec<VersionEntry>>;
+
     /// Retrieves the first pending project from the queue.
     ///
     /// Returns a pending project with the most recent `status_since` or `None`
-    /// if there are no pending projects in the DB.
+    /// if there are no pending projects in the DB or the queue is paused.
     async fn next_job(&self) -> AnyResult<Option<(ProjectId, Version)>>;
 
+    /// Atomically claims the oldest pending project for compilation.
+    ///
+    /// Like [`next_job`](`Self::next_job`), but also moves the project to
+    /// [`ProjectStatus::CompilingSql`] in the same statement, so concurrent
+    /// callers never claim the same project.  Returns `None` if there are
+    /// no pending projects or the queue is paused.
+    async fn claim_next_job(&self) -> AnyResult<Option<(ProjectId, Version)>>;
+
+    /// Pause or resume the compilation queue.
+    ///
+    /// While the queue is paused, [`next_job`](`Self::next_job`) returns
+    /// `None`.  Pending projects stay in the queue and are handed out in the
+    /// original order once the queue is resumed.  The setting is persisted
+    /// in the database.
+    async fn set_queue_paused(&self, paused: bool) -> AnyResult<()>;
+
+    /// Returns `true` if the compilation queue is paused.
+    async fn queue_paused(&self) -> AnyResult<bool>;
+
+    /// Retrieve projects that have been compiling for more than
+    /// `older_than_secs` seconds.
+    ///
+    /// A project stays in [`ProjectStatus::CompilingSql`] or
+    /// [`ProjectStatus::CompilingRust`] state forever if the compiler dies
+    /// while compiling it.  This method allows finding such projects.
+    async fn stale_compiling_projects(&self, older_than_secs: i64) -> AnyResult<Vec<ProjectId>>;
+
+    /// Put projects that have been compiling for more than `older_than_secs`
+    /// seconds back in the compilation queue.
+    ///
+    /// Projects are checked and requeued atomically, so a compilation that
+    /// completes concurrently keeps its status.  Returns the ids of requeued
+    /// projects, ordered by id.
+    async fn requeue_stale_compiling_projects(
+        &self,
+        older_than_secs: i64,
+    ) -> AnyResult<Vec<ProjectId>>;
+
+    /// Retrieve all project configs, ordered by id.
     async fn list_configs(&self) -> AnyResult<Vec<ConfigDescr>>;
 
+    /// Retrieve configs whose project failed to compile, along with the
+    /// project status, ordered by config id.
+    ///
+    /// Pipelines cannot be started from these configs until the project is
+    /// fixed.  Configs without a project are not included.
+    async fn configs_with_failed_projects(&self) -> AnyResult<Vec<(ConfigId, ProjectStatus)>>;
+
     async fn get_config(&self, config_id: ConfigId) -> AnyResult<ConfigDescr>;
 
     /// Create a new project config.
@@ -208,32 +486,116 @@ pub(crate) trait Storage {
     ) -> AnyResult<Version>;
 
     /// Delete project config.
-    async fn delete_config(&self, config_id: ConfigId) -> AnyResult<()>;
+    ///
+    /// Pipelines created from the config are detached from it.  Unless `force`
+    /// is `true`, fails with `DBError::ConfigHasActivePipelines` if any of
+    /// these pipelines hasn't been shut down.
+    async fn delete_config(&self, config_id: ConfigId, force: bool) -> AnyResult<()>;
+
+    /// Retrieve ids of pipelines created from the config that haven't been
+    /// shut down.
+    async fn active_pipelines_for_config(&self, config_id: ConfigId) -> AnyResult<Vec<PipelineId>>;
+
+    /// Count pipelines that haven't been shut down.
+    async fn running_pipeline_count(&self) -> AnyResult<usize>;
+
+    /// Retrieve connectors attached to the configs of pipelines that haven't
+    /// been shut down, ordered by pipeline and connector id.
+    ///
+    /// A connector attached to a config several times is only returned once
+    /// per pipeline.
+    async fn live_connectors(&self) -> AnyResult<Vec<(PipelineId, ConnectorDescr)>>;
 
     /// Get an attached connector.
     async fn get_attached_connector_direction(&self, uuid: &str) -> AnyResult<Direction>;
 
+    /// Retrieve the set of connector types attached to a config.
+    ///
+    /// Returns an empty set if the config does not exist or has no attached
+    /// connectors.
+    async fn config_connector_types(
+        &self,
+        config_id: ConfigId,
+    ) -> AnyResult<HashSet<ConnectorType>>;
+
+    /// Retrieve ids of connectors attached to a config that no longer exist.
+    ///
+    /// Deleting a connector also detaches it from all configs, so this should
+    /// normally return an empty vector.  It is meant as a sanity check before
+    /// running a pipeline, guarding against inconsistent data.
+    async fn validate_config_connectors(&self, config_id: ConfigId) -> AnyResult<Vec<ConnectorId>>;
+
+    /// Retrieve ids of configs that reference a project that no longer
+    /// exists, ordered by id.
+    ///
+    /// Configs without a project are not orphaned.  Deleting a project also
+    /// deletes its configs, so this should normally return an empty vector.
+    /// It is meant as a data-integrity diagnostic.
+    async fn orphaned_configs(&self) -> AnyResult<Vec<ConfigId>>;
+
     /// Insert a new record to the `pipeline` table.
+    ///
+    /// The pipeline is tagged with deployment `environment`, e.g., `prod`.
     async fn new_pipeline(
         &self,
         config_id: ConfigId,
         config_version: Version,
+        environment: &str,
     ) -> AnyResult<PipelineId>;
 
+    /// Record the port the pipeline is listening on.
+    ///
+    /// Fails with `DBError::PortInUse` if another pipeline that hasn't been
+    /// shut down already holds `port`.  Port `0` means that the port isn't
+    /// known and is never considered to be in use.
     async fn pipeline_set_port(&self, pipeline_id: PipelineId, port: u16) -> AnyResult<()>;
 
     /// Set `shutdown` flag to `true`.
     async fn set_pipeline_shutdown(&self, pipeline_id: PipelineId) -> AnyResult<bool>;
 
+    /// Atomically move pipeline from state `from` to state `to`.
+    ///
+    /// Fails with `DBError::InvalidPipelineTransition` if `from -> to` is not
+    /// a valid transition or if the pipeline is not currently in state
+    /// `from`, and with `DBError::UnknownPipeline` if the pipeline doesn't
+    /// exist.
+    async fn transition_pipeline(
+        &self,
+        pipeline_id: PipelineId,
+        from: PipelineStatus,
+        to: PipelineStatus,
+    ) -> AnyResult<()>;
+
     /// Delete `pipeline` from the DB.
     async fn delete_pipeline(&self, pipeline_id: PipelineId) -> AnyResult<bool>;
 
     /// Retrieve project config.
     async fn get_pipeline(&self, pipeline_id: PipelineId) -> AnyResult<PipelineDescr>;
 
-    /// List pipelines associated with `project_id`.
+    /// Retrieve the config the pipeline was created from.
+    ///
+    /// Returns `DBError::UnknownPipeline` if the pipeline doesn't exist,
+    /// `DBError::PipelineHasNoConfig` if its config was deleted, and
+    /// `DBError::UnknownConfig` if the config can't be found.
+    async fn config_for_pipeline(&self, pipeline_id: PipelineId) -> AnyResult<ConfigDescr> {
+        let config_id = self
+            .get_pipeline(pipeline_id)
+            .await?
+            .config_id
+            .ok_or_else(|| anyhow!(DBError::PipelineHasNoConfig(pipeline_id)))?;
+        self.get_config(config_id).await
+    }
+
+    /// Retrieve all pipelines, ordered by id.
     async fn list_pipelines(&self) -> AnyResult<Vec<PipelineDescr>>;
 
+    /// Retrieve pipelines tagged with deployment `environment`, ordered by
+    /// id.
+    async fn list_pipelines_by_environment(
+        &self,
+        environment: &str,
+    ) -> AnyResult<Vec<PipelineDescr>>;
+
     /// Create a new connector.
     async fn new_connector(
         &self,
@@ -243,7 +605,7 @@ pub(crate) trait Storage {
         config: &str,
     ) -> AnyResult<ConnectorId>;
 
-    /// Retrieve connectors list from the DB.
+    /// Retrieve all connectors, ordered by id.
     async fn list_connectors(&self) -> AnyResult<Vec<ConnectorDescr>>;
 
     /// Retrieve connector descriptor.
@@ -264,4 +626,66 @@ pub(crate) trait Storage {
     ///
     /// This will delete all connector configs and pipelines.
     async fn delete_connector(&self, connector_id: ConnectorId) -> AnyResult<()>;
+
+    /// Tag a connector.
+    ///
+    /// Adding a tag the connector already has is a no-op.
+    async fn add_connector_tag(&self, connector_id: ConnectorId, tag: &str) -> AnyResult<()>;
+
+    /// Remove a tag from a connector.
+    ///
+    /// Removing a tag the connector doesn't have is a no-op.
+    async fn remove_connector_tag(&self, connector_id: ConnectorId, tag: &str) -> AnyResult<()>;
+
+    /// Retrieve all connectors with the given tag, ordered by id.
+    async fn list_connectors_by_tag(&self, tag: &str) -> AnyResult<Vec<ConnectorDescr>>;
+
+    /// Serialize all connectors, ordered by id, into a YAML list of
+    /// [`ConnectorSpec`]s that can be loaded back with
+    /// [`import_connectors`](`Self::import_connectors`).
+    ///
+    /// Secrets in connector configs are replaced with `***` by
+    /// [`redact_secrets`], so that the document can be stored outside the
+    /// manager.  They must be filled in before importing the document.
+    async fn export_connectors(&self) -> AnyResult<String> {
+        let specs: Vec<ConnectorSpec> = self
+            .list_connectors()
+            .await?
+            .into_iter()
+            .map(|c| ConnectorSpec {
+                name: c.name,
+                description: c.description,
+                typ: c.typ,
+                config: redact_secrets(&c.config),
+            })
+            .collect();
+        Ok(serde_yaml::to_string(&specs)?)
+    }
+
+    /// Create connectors from a YAML document produced by
+    /// [`export_connectors`](`Self::export_connectors`).
+    ///
+    /// Returns ids of the new connectors in the order they appear in the
+    /// document.  Fails with `DBError::DuplicateConnectorName` if a name is
+    /// used twice in the document or by an existing connector.  Connectors
+    /// are created atomically: if any of them can't be created, none are.
+    async fn import_connectors(&mut self, yaml: &str) -> AnyResult<Vec<ConnectorId>>;
+}
+
+/// Parse a document produced by [`Storage::export_connectors`], checking
+/// that its connector names are unique and not among `existing_names`.
+pub(crate) fn parse_connector_specs(
+    yaml: &str,
+    existing_names: impl IntoIterator<Item = String>,
+) -> AnyResult<Vec<ConnectorSpec>> {
+    let specs: Vec<ConnectorSpec> = serde_yaml::from_str(yaml)?;
+
+    let mut names: HashSet<String> = existing_names.into_iter().collect();
+    for spec in specs.iter() {
+        if !names.insert(spec.name.clone()) {
+            return Err(anyhow!(DBError::DuplicateConnectorName(spec.name.clone())));
+        }
+    }
+
+    Ok(specs)
 }
@@ -69,7 +69,7 @@ pub(crate) use compiler::{Compiler, ProjectStatus};
 pub(crate) use config::ManagerConfig;
 use db::{
     storage::Storage, AttachedConnector, AttachedConnectorId, ConfigId, ConnectorId, ConnectorType,
-    DBError, PipelineId, ProjectDB, ProjectDescr, ProjectId, Version,
+    DBError, DbMetrics, PipelineId, ProjectDB, ProjectDescr, ProjectId, Version,
 };
 use runner::{LocalRunner, Runner, RunnerError};
 
@@ -133,7 +133,8 @@ observed by the user is outdated, so the request is rejected."
         update_connector,
         connector_status,
         delete_connector,
-        http_input
+        http_input,
+        db_metrics
     ),
     components(schemas(
         compiler::SqlCompilerMessage,
@@ -143,6 +144,7 @@ observed by the user is outdated, so the request is rejected."
         db::ConnectorType,
         db::ConfigDescr,
         db::PipelineDescr,
+        db::DbMetrics,
         dbsp_adapters::PipelineConfig,
         dbsp_adapters::InputEndpointConfig,
         dbsp_adapters::OutputEndpointConfig,
@@ -227,26 +229,15 @@ struct ServerState {
     // transaction conflicts.  The server must avoid holding this lock
     // for a long time to avoid blocking concurrent requests.
     db: Arc<Mutex<ProjectDB>>,
-    // Dropping this handle kills the compiler task.
-    _compiler: Compiler,
     runner: Runner,
     config: ManagerConfig,
 }
 
 impl ServerState {
-    async fn new(
-        config: ManagerConfig,
-        db: Arc<Mutex<ProjectDB>>,
-        compiler: Compiler,
-    ) -> AnyResult<Self> {
+    async fn new(config: ManagerConfig, db: Arc<Mutex<ProjectDB>>) -> AnyResult<Self> {
         let runner = Runner::Local(LocalRunner::new(db.clone(), &config)?);
 
-        Ok(Self {
-            db,
-            _compiler: compiler,
-            runner,
-            config,
-        })
+        Ok(Self { db, runner, config })
     }
 }
 
@@ -293,19 +284,35 @@ fn run(config: ManagerConfig) -> AnyResult<()> {
         // Since we don't trust any file system state after restart,
         // reset all projects to `ProjectStatus::None`, which will force
         // us to recompile projects before running them.
-        db.lock().await.reset_project_status().await?;
+        if !config.read_only {
+            db.lock().await.reset_project_status().await?;
+        }
         let openapi = ApiDoc::openapi();
 
-        let state = WebData::new(ServerState::new(config, db, compiler).await?);
+        let state = WebData::new(ServerState::new(config, db.clone()).await?);
 
-        let server = HttpServer::new(move || {
-            let app = App::new()
-                .wrap(Logger::default())
-                .wrap(Condition::new(dev_mode, actix_cors::Cors::permissive()));
+        let server = HttpServer::new({
+            let state = state.clone();
+            move || {
+                let app = App::new()
+                    .wrap(Logger::default())
+                    .wrap(Condition::new(dev_mode, actix_cors::Cors::permissive()));
 
-            build_app(app, state.clone(), openapi.clone())
+                build_app(app, state.clone(), openapi.clone())
+            }
         });
         server.listen(listener)?.run().await?;
+
+        // The server has stopped and dropped its workers.  Release the
+        // remaining references to the database before closing it: wait for
+        // the compiler task to terminate and drop the app state, which owns
+        // the runner.
+        compiler.shutdown().await;
+        drop(state);
+        match Arc::try_unwrap(db) {
+            Ok(db) => db.into_inner().close().await?,
+            Err(_) => warn!("database still in use on shutdown; skipping clean close"),
+        }
         Ok(())
     })
 }
@@ -317,19 +324,6 @@ fn build_app<T>(app: App<T>, state: WebData<ServerState>, openapi: OpenApiDoc) -
 where
     T: ServiceFactory<ServiceRequest, Config = (), Error = ActixError, InitError = ()>,
 {
-    // Creates a dictionary of static files indexed by file name.
-    let generated = generate();
-
-    // Extract the contents of `index.html`, so we can serve it on the
-    // root endpoint (`/`), while other files are served via the`/static`
-    // endpoint.
-    let index_data = match generated.get("index.html") {
-        None => "<html><head><title>DBSP manager</title></head></html>"
-            .as_bytes()
-            .to_owned(),
-        Some(resource) => resource.data.to_owned(),
-    };
-
     let app = app
         .app_data(state.clone())
         .service(list_projects)
@@ -358,9 +352,38 @@ where
         .service(connector_status)
         .service(delete_connector)
         .service(http_input)
+        .service(db_metrics)
         .service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-doc/openapi.json", openapi));
 
-    if let Some(static_html) = &state.config.static_html {
+    mount_ui(app, &state.config)
+}
+
+/// Serve the web UI: `index.html` on the root endpoint and other files on
+/// the `/static` endpoint.
+///
+/// Leaves `app` unmodified if `config.serve_ui` is `false`.
+fn mount_ui<T>(app: App<T>, config: &ManagerConfig) -> App<T>
+where
+    T: ServiceFactory<ServiceRequest, Config = (), Error = ActixError, InitError = ()>,
+{
+    if !config.serve_ui {
+        return app;
+    }
+
+    // Creates a dictionary of static files indexed by file name.
+    let generated = generate();
+
+    // Extract the contents of `index.html`, so we can serve it on the
+    // root endpoint (`/`), while other files are served via the`/static`
+    // endpoint.
+    let index_data = match generated.get("index.html") {
+        None => "<html><head><title>DBSP manager</title></head></html>"
+            .as_bytes()
+            .to_owned(),
+        Some(resource) => resource.data.to_owned(),
+    };
+
+    if let Some(static_html) = &config.static_html {
         // Serve static contents from the file system.
         app.route("/", web::get().to(index))
             .service(fs::Files::new("/static", static_html).show_files_listing())
@@ -410,6 +433,15 @@ fn http_resp_from_error(error: &AnyError) -> HttpResponse {
             DBError::UnknownConfig(_) => HttpResponse::NotFound(),
             DBError::UnknownPipeline(_) => HttpResponse::NotFound(),
             DBError::UnknownConnector(_) => HttpResponse::NotFound(),
+            DBError::ConfigHasActivePipelines(_) => HttpResponse::Conflict(),
+            DBError::DuplicateConnectorName(_) => HttpResponse::Conflict(),
+            DBError::InvalidPipelineTransition(..) => HttpResponse::Conflict(),
+            DBError::PipelineHasNoConfig(_) => HttpResponse::NotFound(),
+            DBError::PortInUse(_) => HttpResponse::Conflict(),
+            DBError::ReadOnly => HttpResponse::Forbidden(),
+            DBError::InvalidProjectStatus(_) => HttpResponse::InternalServerError(),
+            DBError::UnknownArchive(_) => HttpResponse::NotFound(),
+            DBError::InvalidName(_) => HttpResponse::BadRequest(),
         }
         .json(ErrorResponse::new(&message))
     } else if let Some(runner_error) = error.downcast_ref::<RunnerError>() {
@@ -601,6 +633,12 @@ struct NewProjectRequest {
     /// SQL code of the project.
     #[schema(example = "CREATE TABLE Example(name varchar);")]
     code: String,
+    /// Client-generated key that identifies this request across retries.
+    ///
+    /// If a project was already created with the same key, the request
+    /// returns that project instead of creating a new one.
+    #[serde(default)]
+    idempotency_key: Option<String>,
 }
 
 /// Response to a new project request.
@@ -609,7 +647,9 @@ struct NewProjectResponse {
     /// Id of the newly created project.
     #[schema(example = 42)]
     project_id: ProjectId,
-    /// Initial project version (this field is always set to 1).
+    /// Initial project version (this field is always set to 1, unless the
+    /// request matched an existing project by its idempotency key, in which
+    /// case it's the current version of that project).
     #[schema(example = 1)]
     version: Version,
 }
@@ -623,6 +663,10 @@ struct NewProjectResponse {
     request_body = NewProjectRequest,
     responses(
         (status = CREATED, description = "Project created successfully", body = NewProjectResponse),
+        (status = BAD_REQUEST
+            , description = "The project name is empty, too long, or contains invalid characters."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("Invalid name \"a/b\": names must be non-empty, must not exceed the maximum length, and must not contain control characters, '/' or '\\'"))),
         (status = CONFLICT
             , description = "A project with this name already exists in the database."
             , body = ErrorResponse
@@ -660,7 +704,12 @@ async fn do_new_project(
         .db
         .lock()
         .await
-        .new_project(&request.name, &request.description, &request.code)
+        .new_project(
+            &request.name,
+            &request.description,
+            &request.code,
+            request.idempotency_key.as_deref(),
+        )
         .await
         .map(|(project_id, version)| {
             HttpResponse::Created()
@@ -705,6 +754,10 @@ struct UpdateProjectResponse {
     request_body = UpdateProjectRequest,
     responses(
         (status = OK, description = "Project updated successfully.", body = UpdateProjectResponse),
+        (status = BAD_REQUEST
+            , description = "The project name is empty, too long, or contains invalid characters."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("Invalid name \"a/b\": names must be non-empty, must not exceed the maximum length, and must not contain control characters, '/' or '\\'"))),
         (status = NOT_FOUND
             , description = "Specified `project_id` does not exist in the database."
             , body = ErrorResponse
@@ -1020,6 +1073,10 @@ async fn update_config(
             , description = "Specified `config_id` does not exist in the database."
             , body = ErrorResponse
             , example = json!(ErrorResponse::new("Unknown config id '5'"))),
+        (status = CONFLICT
+            , description = "The configuration is used by pipelines that haven't been shut down."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("Project config id '5' is used by pipelines that haven't been shut down"))),
     ),
     params(
         ("config_id" = i64, Path, description = "Unique configuration identifier")
@@ -1039,7 +1096,7 @@ async fn delete_config(state: WebData<ServerState>, req: HttpRequest) -> impl Re
         .db
         .lock()
         .await
-        .delete_config(config_id)
+        .delete_config(config_id, false)
         .await
         .map(|_| HttpResponse::Ok().finish())
         .unwrap_or_else(|e| http_resp_from_error(&e))
@@ -1112,6 +1169,10 @@ pub(self) struct NewPipelineRequest {
     config_id: ConfigId,
     /// Latest config version known to the client.
     config_version: Version,
+    /// Deployment environment to tag the pipeline with, e.g., `prod`.
+    /// Defaults to the manager's `default_pipeline_environment`.
+    #[serde(default)]
+    environment: Option<String>,
 }
 
 /// Response to a pipeline creation request.
@@ -1454,6 +1515,28 @@ async fn list_connectors(state: WebData<ServerState>) -> impl Responder {
         .unwrap_or_else(|e| http_resp_from_error(&e))
 }
 
+/// Count projects, configs, pipelines, and connectors stored by the manager.
+#[utoipa::path(
+    responses(
+        (status = OK, description = "Database metrics retrieved successfully", body = DbMetrics),
+    ),
+)]
+#[get("/metrics")]
+async fn db_metrics(state: WebData<ServerState>) -> impl Responder {
+    state
+        .db
+        .lock()
+        .await
+        .metrics()
+        .await
+        .map(|metrics| {
+            HttpResponse::Ok()
+                .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+                .json(metrics)
+        })
+        .unwrap_or_else(|e| http_resp_from_error(&e))
+}
+
 /// Request to create a new connector.
 #[derive(Deserialize, ToSchema)]
 pub(self) struct NewConnectorRequest {
@@ -1464,6 +1547,9 @@ pub(self) struct NewConnectorRequest {
     /// connector type.
     typ: ConnectorType,
     /// connector config.
+    ///
+    /// When empty, the connector is created with a config template for its
+    /// type.
     config: String,
 }
 
@@ -1479,6 +1565,10 @@ struct NewConnectorResponse {
     request_body = NewConnectorRequest,
     responses(
         (status = OK, description = "connector successfully created.", body = NewConnectorResponse),
+        (status = BAD_REQUEST
+            , description = "The connector name is empty, too long, or contains invalid characters."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("Invalid name \"a/b\": names must be non-empty, must not exceed the maximum length, and must not contain control characters, '/' or '\\'"))),
     ),
     tag = "Connector"
 )]
@@ -1487,16 +1577,17 @@ async fn new_connector(
     state: WebData<ServerState>,
     request: web::Json<NewConnectorRequest>,
 ) -> impl Responder {
+    let config = if request.config.trim().is_empty() {
+        request.typ.default_config()
+    } else {
+        request.config.clone()
+    };
+
     state
         .db
         .lock()
         .await
-        .new_connector(
-            &request.name,
-            &request.description,
-            request.typ,
-            &request.config,
-        )
+        .new_connector(&request.name, &request.description, request.typ, &config)
         .await
         .map(|connector_id| {
             HttpResponse::Ok()
@@ -1531,6 +1622,10 @@ struct UpdateConnectorResponse {}
     request_body = UpdateConnectorRequest,
     responses(
         (status = OK, description = "connector successfully updated.", body = UpdateConnectorResponse),
+        (status = BAD_REQUEST
+            , description = "The connector name is empty, too long, or contains invalid characters."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("Invalid name \"a/b\": names must be non-empty, must not exceed the maximum length, and must not contain control characters, '/' or '\\'"))),
         (status = NOT_FOUND
             , description = "Specified `connector_id` does not exist in the database."
             , body = ErrorResponse
@@ -1691,3 +1786,34 @@ async fn http_input(
         .await
         .unwrap_or_else(|e| http_resp_from_error(&e))
 }
+
+#[cfg(test)]
+mod test {
+    use super::mount_ui;
+    use crate::ManagerConfig;
+    use actix_web::{http::StatusCode, test, App};
+    use clap::Parser;
+
+    /// Status of a `GET uri` request to an app that only serves the UI.
+    async fn ui_status(args: &[&str], uri: &str) -> StatusCode {
+        let config =
+            ManagerConfig::try_parse_from(["pipeline-manager"].iter().chain(args)).unwrap();
+        let app = test::init_service(mount_ui(App::new(), &config)).await;
+        let req = test::TestRequest::get().uri(uri).to_request();
+        test::call_service(&app, req).await.status()
+    }
+
+    #[actix_web::test]
+    async fn serve_ui() {
+        assert_eq!(ui_status(&[], "/").await, StatusCode::OK);
+
+        assert_eq!(
+            ui_status(&["--serve-ui", "false"], "/").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            ui_status(&["--serve-ui", "false"], "/static/index.html").await,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
-    db::storage::Storage, db::AttachedConnector, db::ConfigDescr, Direction, ErrorResponse,
-    ManagerConfig, NewPipelineRequest, NewPipelineResponse, PipelineId, ProjectDB, ProjectId,
-    ProjectStatus, Version,
+    db::redact_secrets, db::storage::Storage, db::AttachedConnector, db::ConfigDescr,
+    db::ParsedConfig, ConfigId, Direction, ErrorResponse, ManagerConfig, NewPipelineRequest,
+    NewPipelineResponse, PipelineId, ProjectDB, ProjectId, ProjectStatus, Version,
 };
 use actix_web::{
     http::{Error, Method},
@@ -11,11 +11,14 @@ use actix_web::{
 use actix_web_actors::ws::handshake;
 use anyhow::{Error as AnyError, Result as AnyResult};
 use awc::Client;
+use chrono::{DateTime, Utc};
+use dbsp_adapters::{InputEndpointConfig, OutputEndpointConfig, PipelineConfig};
 use futures_util::StreamExt;
 use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{
-    error::Error as StdError, fmt, fmt::Display, path::Path, pin::Pin, process::Stdio, sync::Arc,
+    borrow::Cow, collections::BTreeMap, error::Error as StdError, fmt, fmt::Display, path::Path,
+    pin::Pin, process::Stdio, sync::Arc,
 };
 use tokio::{
     fs,
@@ -80,14 +83,131 @@ pub struct LocalRunner {
 /// `/metadata` endpoint.  The pipeline doesn't enforce any particular
 /// format, schema, or semantics of the metadata string, but various tools
 /// and UIs may.
-#[derive(Serialize)]
-struct PipelineMetadata {
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub(crate) struct PipelineMetadata {
     /// Project id.
     project_id: ProjectId,
-    /// Project version.
-    version: Version,
+    /// Project config id.
+    config_id: ConfigId,
+    /// Version of the project config the pipeline was started with.
+    config_version: Version,
+    /// Pipeline creation time.
+    created: DateTime<Utc>,
     /// Project code.
     code: String,
+    /// User-defined labels, taken from the `labels` section of the project
+    /// config.  Labels are attached to the metrics exported by the pipeline.
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    /// Rust toolchain the pipeline was compiled with, or `None` if the
+    /// manager doesn't pin the toolchain and the default one was used.
+    #[serde(default)]
+    rust_toolchain: Option<String>,
+}
+
+/// Returns an error message if starting another pipeline while `running`
+/// pipelines haven't been shut down would exceed the `max_running_pipelines`
+/// limit `max`.
+fn pipeline_limit_error(running: usize, max: Option<usize>) -> Option<String> {
+    match max {
+        Some(max) if running >= max => Some(format!(
+            "Cannot start a new pipeline: {running} pipeline(s) are already running, \
+             and at most {max} are allowed by the 'max_running_pipelines' setting. \
+             Shut down an existing pipeline first."
+        )),
+        _ => None,
+    }
+}
+
+/// Write pipeline metadata to `path` in the JSON format expected by the
+/// pipeline executable's `--metadata-file` argument.
+pub(crate) async fn write_pipeline_metadata(
+    metadata: &PipelineMetadata,
+    path: &Path,
+) -> AnyResult<()> {
+    let json = serde_json::to_string(metadata)?;
+
+    fs::write(path, json).await.map_err(|e| {
+        AnyError::msg(format!(
+            "failed to write pipeline metadata '{}': {e}",
+            path.display()
+        ))
+    })
+}
+
+/// Extract user-defined pipeline labels from a parsed project config.
+///
+/// Labels become Prometheus labels, so their names must be valid Prometheus
+/// label names.
+fn pipeline_labels(config: &ParsedConfig) -> AnyResult<BTreeMap<String, String>> {
+    let labels = config.labels.clone();
+    for name in labels.keys() {
+        let mut chars = name.chars();
+        let valid = chars
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid || name.starts_with("__") {
+            return Err(AnyError::msg(format!(
+                "invalid pipeline label name '{name}'"
+            )));
+        }
+    }
+
+    Ok(labels)
+}
+
+/// Write a Prometheus file-based service discovery config for a pipeline
+/// listening on `port` to `path`.
+///
+/// Metrics scraped from the pipeline are labeled with the pipeline, project
+/// and config ids and with the user-defined labels from `metadata`.
+pub(crate) async fn write_prometheus_config(
+    pipeline_id: PipelineId,
+    port: u16,
+    metadata: &PipelineMetadata,
+    path: &Path,
+) -> AnyResult<()> {
+    #[derive(Serialize)]
+    struct TargetGroup {
+        targets: Vec<String>,
+        labels: BTreeMap<String, String>,
+    }
+
+    let mut labels = metadata.labels.clone();
+    labels.insert("pipeline_id".to_string(), pipeline_id.to_string());
+    labels.insert("project_id".to_string(), metadata.project_id.to_string());
+    labels.insert("config_id".to_string(), metadata.config_id.to_string());
+    labels.insert(
+        "config_version".to_string(),
+        metadata.config_version.to_string(),
+    );
+
+    let yaml = serde_yaml::to_string(&[TargetGroup {
+        targets: vec![format!("localhost:{port}")],
+        labels,
+    }])?;
+
+    fs::write(path, yaml).await.map_err(|e| {
+        AnyError::msg(format!(
+            "failed to write Prometheus config '{}': {e}",
+            path.display()
+        ))
+    })
+}
+
+/// Write pipeline configuration to `path` in the YAML format expected by
+/// the pipeline executable's `--config-file` argument.
+pub(crate) async fn write_pipeline_config(config: &PipelineConfig, path: &Path) -> AnyResult<()> {
+    let yaml = serde_yaml::to_string(config)?;
+    log::debug!("Pipeline config is '{}'", redact_secrets(&yaml));
+
+    fs::write(path, yaml).await.map_err(|e| {
+        AnyError::msg(format!(
+            "failed to write pipeline config '{}': {e}",
+            path.display()
+        ))
+    })
 }
 
 impl Runner {
@@ -196,14 +316,38 @@ impl LocalRunner {
             return Ok(HttpResponse::Conflict().body("Project hasn't been compiled yet"));
         };
 
+        // Preflight: connectors that refer to relations dropped from the
+        // project will fail to start.
+        for warning in db
+            .detect_config_schema_drift(config_descr.config_id)
+            .await?
+        {
+            log::warn!("Config {}: {warning}", config_descr.config_id);
+        }
+
+        // Check: starting the pipeline doesn't exceed `max_running_pipelines`.
+        // The check is race-free, since we hold the database lock until the
+        // pipeline has been recorded.
+        if let Some(error) = pipeline_limit_error(
+            db.running_pipeline_count().await?,
+            self.config.max_running_pipelines,
+        ) {
+            return Ok(HttpResponse::Conflict().body(error));
+        }
+
+        let environment = request
+            .environment
+            .as_deref()
+            .unwrap_or(&self.config.default_pipeline_environment);
         let pipeline_id = db
-            .new_pipeline(request.config_id, request.config_version)
+            .new_pipeline(request.config_id, request.config_version, environment)
             .await?;
         db.add_pipeline_to_config(config_descr.config_id, pipeline_id)
             .await?;
 
         // Run the pipeline executable.
-        let mut pipeline_process = self.start(&db, request, &config_descr, pipeline_id).await?;
+        let (mut pipeline_process, metadata) =
+            self.start(&db, request, &config_descr, pipeline_id).await?;
 
         // Unlock db -- the next part can be slow.
         drop(db);
@@ -211,16 +355,29 @@ impl LocalRunner {
         match Self::wait_for_startup(&self.config.log_file_path(pipeline_id)).await {
             Ok(port) => {
                 // Store pipeline in the database.
-                if let Err(e) = self
-                    .db
-                    .lock()
-                    .await
-                    .pipeline_set_port(pipeline_id, port)
-                    .await
-                {
+                let db = self.db.lock().await;
+                if let Err(e) = db.pipeline_set_port(pipeline_id, port).await {
+                    // Remove the pipeline so it does not count towards the
+                    // running pipelines.
                     let _ = pipeline_process.kill().await;
+                    db.delete_pipeline(pipeline_id).await?;
                     return Err(e);
                 };
+                drop(db);
+                // Let Prometheus know where to scrape pipeline metrics.  This is
+                // only used for metrics discovery, so the pipeline keeps running
+                // if it fails.
+                let prometheus_file_path = self.config.prometheus_file_path(pipeline_id);
+                let prometheus_result = match create_dir_all(self.config.prometheus_dir()).await {
+                    Ok(()) => {
+                        write_prometheus_config(pipeline_id, port, &metadata, &prometheus_file_path)
+                            .await
+                    }
+                    Err(e) => Err(e.into()),
+                };
+                if let Err(e) = prometheus_result {
+                    log::warn!("Failed to write Prometheus config of pipeline {pipeline_id}: {e}");
+                }
                 let json_string =
                     serde_json::to_string(&NewPipelineResponse { pipeline_id, port }).unwrap();
 
@@ -256,8 +413,9 @@ impl LocalRunner {
             return Ok(response);
         }
 
-        // Delete pipeline directory.
+        // Delete pipeline directory and its Prometheus config.
         remove_dir_all(self.config.pipeline_dir(pipeline_id)).await?;
+        let _ = fs::remove_file(self.config.prometheus_file_path(pipeline_id)).await;
         db.delete_pipeline(pipeline_id).await?;
 
         Ok(HttpResponse::Ok().json("Pipeline successfully deleted."))
@@ -363,7 +521,7 @@ impl LocalRunner {
         request: &NewPipelineRequest,
         config_descr: &ConfigDescr,
         pipeline_id: PipelineId,
-    ) -> AnyResult<Child> {
+    ) -> AnyResult<(Child, PipelineMetadata)> {
         assert!(
             config_descr.project_id.is_some(),
             "pre-condition for start(): config.project_id is set"
@@ -371,73 +529,73 @@ impl LocalRunner {
         let project_id = config_descr.project_id.unwrap();
 
         // Assemble the final config by including all attached connectors.
-        async fn generate_attached_connector_config(
+        async fn attached_connector_config(
             db: &ProjectDB,
-            config: &mut String,
             ac: &AttachedConnector,
-        ) -> AnyResult<()> {
-            let ident = 4;
-            config.push_str(format!("{:ident$}{}:\n", "", ac.uuid.as_str()).as_str());
-            let ident = 8;
-            config.push_str(format!("{:ident$}stream: {}\n", "", ac.config.as_str()).as_str());
+        ) -> AnyResult<String> {
             let connector = db.get_connector(ac.connector_id).await?;
-            for config_line in connector.config.lines() {
-                config.push_str(format!("{:ident$}{config_line}\n", "").as_str());
-            }
-            Ok(())
+            Ok(format!("stream: {}\n{}", ac.config, connector.config))
         }
-        async fn add_debug_websocket(config: &mut String, ac: &AttachedConnector) -> AnyResult<()> {
-            let ident = 4;
-            config.push_str(format!("{:ident$}debug-{}:\n", "", ac.uuid.as_str()).as_str());
-            let ident = 8;
-            config.push_str(format!("{:ident$}stream: {}\n", "", ac.config.as_str()).as_str());
-            for config_line in ["transport:", "     name: http", "format:", "    name: csv"].iter()
-            {
-                config.push_str(format!("{:ident$}{config_line}\n", "").as_str());
-            }
-            Ok(())
+        fn debug_websocket_config(ac: &AttachedConnector) -> String {
+            format!(
+                "stream: {}\ntransport:\n    name: http\nformat:\n    name: csv\n",
+                ac.config
+            )
         }
 
-        let mut config = config_descr.config.clone();
-        config.push_str("inputs:\n");
+        let parsed_config = config_descr.parse_config()?;
+        let labels = pipeline_labels(&parsed_config)?;
+        let mut config = PipelineConfig {
+            global: parsed_config.global,
+            inputs: BTreeMap::new(),
+            outputs: BTreeMap::new(),
+        };
         for ac in config_descr
             .attached_connectors
             .iter()
             .filter(|ac| ac.direction == Direction::Input)
         {
-            generate_attached_connector_config(db, &mut config, ac).await?;
+            let endpoint: InputEndpointConfig =
+                serde_yaml::from_str(&attached_connector_config(db, ac).await?)?;
+            config.inputs.insert(Cow::from(ac.uuid.clone()), endpoint);
         }
-        config.push_str("outputs:\n");
         for ac in config_descr
             .attached_connectors
             .iter()
             .filter(|ac| ac.direction == Direction::Output)
         {
-            generate_attached_connector_config(db, &mut config, ac).await?;
-            add_debug_websocket(&mut config, ac).await?;
+            let endpoint: OutputEndpointConfig =
+                serde_yaml::from_str(&attached_connector_config(db, ac).await?)?;
+            config.outputs.insert(Cow::from(ac.uuid.clone()), endpoint);
+            let debug_endpoint: OutputEndpointConfig =
+                serde_yaml::from_str(&debug_websocket_config(ac))?;
+            config
+                .outputs
+                .insert(Cow::from(format!("debug-{}", ac.uuid)), debug_endpoint);
         }
-        log::debug!("Pipeline config is '{}'", config);
 
         // Create pipeline directory (delete old directory if exists); write metadata
         // and config files to it.
         let pipeline_dir = self.config.pipeline_dir(pipeline_id);
         create_dir_all(&pipeline_dir).await?;
         let config_file_path = self.config.config_file_path(pipeline_id);
-        fs::write(&config_file_path, config.as_str()).await?;
+        write_pipeline_config(&config, &config_file_path).await?;
 
         let (_version, code) = db.project_code(project_id).await?;
 
+        let pipeline_descr = db.get_pipeline(pipeline_id).await?;
+
         let metadata = PipelineMetadata {
             project_id,
-            version: request.config_version,
+            config_id: config_descr.config_id,
+            config_version: request.config_version,
+            created: pipeline_descr.created,
             code,
+            labels,
+            rust_toolchain: self.config.rust_toolchain.clone(),
         };
         let metadata_file_path = self.config.metadata_file_path(pipeline_id);
-        fs::write(
-            &metadata_file_path,
-            serde_json::to_string(&metadata).unwrap(),
-        )
-        .await?;
+        write_pipeline_metadata(&metadata, &metadata_file_path).await?;
 
         let log_file_path = self.config.log_file_path(pipeline_id);
         let log_file = File::create(&log_file_path).await?;
@@ -461,7 +619,7 @@ impl LocalRunner {
             .spawn()
             .map_err(|e| AnyError::msg(format!("failed to run '{}': {e}", executable.display())))?;
 
-        Ok(pipeline_process)
+        Ok((pipeline_process, metadata))
     }
 
     /// Monitor pipeline log until either port number or error shows up or
@@ -564,3 +722,144 @@ impl LocalRunner {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        pipeline_labels, pipeline_limit_error, write_pipeline_config, write_pipeline_metadata,
+        write_prometheus_config, PipelineMetadata,
+    };
+    use crate::{db::ParsedConfig, ConfigId, PipelineId, ProjectId};
+    use chrono::{TimeZone, Utc};
+    use dbsp_adapters::PipelineConfig;
+    use std::collections::BTreeMap;
+
+    fn parse_labels(yaml: &str) -> anyhow::Result<BTreeMap<String, String>> {
+        pipeline_labels(&ParsedConfig::parse(yaml)?)
+    }
+
+    fn test_metadata(labels: BTreeMap<String, String>) -> PipelineMetadata {
+        PipelineMetadata {
+            project_id: ProjectId(1),
+            config_id: ConfigId(2),
+            config_version: serde_json::from_str("3").unwrap(),
+            created: Utc.timestamp_opt(1_680_000_000, 0).unwrap(),
+            code: "create table t1(c1 integer);".to_string(),
+            labels,
+            rust_toolchain: Some("1.69.0".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn pipeline_metadata_roundtrip() {
+        let labels = parse_labels("workers: 2\nlabels:\n  team: analytics\n  env: prod\n").unwrap();
+        let metadata = test_metadata(labels);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("metadata.json");
+        write_pipeline_metadata(&metadata, &path).await.unwrap();
+
+        let parsed: PipelineMetadata =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(metadata, parsed);
+        assert_eq!(parsed.labels["team"], "analytics");
+        assert_eq!(parsed.labels["env"], "prod");
+    }
+
+    #[test]
+    fn pipeline_label_names() {
+        assert!(parse_labels("").unwrap().is_empty());
+        assert!(parse_labels("workers: 2").unwrap().is_empty());
+        assert!(parse_labels("labels:\n  _valid_1: x").is_ok());
+        assert!(parse_labels("labels:\n  1invalid: x").is_err());
+        assert!(parse_labels("labels:\n  in-valid: x").is_err());
+        assert!(parse_labels("labels:\n  __reserved: x").is_err());
+    }
+
+    #[test]
+    fn pipeline_limit() {
+        assert_eq!(pipeline_limit_error(0, None), None);
+        assert_eq!(pipeline_limit_error(100, None), None);
+        assert_eq!(pipeline_limit_error(0, Some(2)), None);
+        assert_eq!(pipeline_limit_error(1, Some(2)), None);
+
+        let error = pipeline_limit_error(2, Some(2)).unwrap();
+        assert!(error.contains("2 pipeline(s) are already running"));
+        assert!(error.contains("max_running_pipelines"));
+        assert!(pipeline_limit_error(3, Some(2)).is_some());
+
+        // A limit of 0 disables starting pipelines altogether.
+        assert!(pipeline_limit_error(0, Some(0)).is_some());
+    }
+
+    #[tokio::test]
+    async fn prometheus_config_labels() {
+        let labels = parse_labels("labels:\n  team: analytics\n").unwrap();
+        let metadata = test_metadata(labels);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("pipeline5.yaml");
+        write_prometheus_config(PipelineId(5), 8080, &metadata, &path)
+            .await
+            .unwrap();
+
+        let parsed: serde_yaml::Value =
+            serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let groups = parsed.as_sequence().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0]["targets"][0], "localhost:8080");
+
+        let labels = &groups[0]["labels"];
+        assert_eq!(labels["team"], "analytics");
+        assert_eq!(labels["pipeline_id"], "5");
+        assert_eq!(labels["project_id"], "1");
+        assert_eq!(labels["config_id"], "2");
+        assert_eq!(labels["config_version"], "3");
+    }
+
+    #[tokio::test]
+    async fn pipeline_config_roundtrip() {
+        let yaml = r#"
+workers: 4
+cpu_profiler: true
+min_batch_size_records: 100
+max_buffering_delay_usecs: 500
+inputs:
+    in1:
+        stream: T1
+        transport:
+            name: file
+            config:
+                path: input.csv
+        format:
+            name: csv
+outputs:
+    out1:
+        stream: V1
+        transport:
+            name: kafka
+            config:
+                topic: out
+        format:
+            name: csv
+        max_buffered_records: 10
+"#;
+        let config: PipelineConfig = serde_yaml::from_str(yaml).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("config.yaml");
+        write_pipeline_config(&config, &path).await.unwrap();
+
+        let parsed: PipelineConfig =
+            serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            serde_yaml::to_value(&config).unwrap(),
+            serde_yaml::to_value(&parsed).unwrap()
+        );
+        assert_eq!(parsed.global.workers, 4);
+        assert!(parsed.global.cpu_profiler);
+        assert_eq!(parsed.inputs["in1"].stream, "T1");
+        assert_eq!(parsed.inputs["in1"].transport.name, "file");
+        assert_eq!(parsed.outputs["out1"].max_buffered_records, 10);
+    }
+}